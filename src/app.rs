@@ -1,10 +1,11 @@
-use std::{rc::Rc, sync::{Arc, atomic::{AtomicBool, Ordering}}, cell::RefCell, collections::HashMap};
+use std::{rc::Rc, sync::{Arc, atomic::{AtomicBool, Ordering}}, cell::RefCell, collections::HashMap, thread};
 
 use egui::{Context, Vec2, Color32};
 use egui_notify::Toasts;
 
 use parking_lot::Mutex;
-use crate::{ui::{ WindowContainer, gallery_ui::GalleryUI, data_ui::DataUI, self, ToastsRef, widgets::autocomplete::AutocompleteOption}, tags::{self, TagDataRef}, config::Config, data::{self, EntryId}};
+use crate::import::FolderWatcher;
+use crate::{ui::{ WindowContainer, gallery_ui::GalleryUI, data_ui::DataUI, duplicates_ui::DuplicatesUI, self, ToastsRef, widgets::autocomplete::AutocompleteOption}, tags::{self, TagDataRef}, config::Config, data::{self, EntryId}};
 
 pub struct App {
     pub shared_state: Rc<SharedState>,
@@ -32,6 +33,10 @@ pub struct SharedState {
     pub database_unlocked: UpdateFlag,
     pub disable_navbar: UpdateList<String>,
     pub database_changed: UpdateFlag,
+    // a search to show in the gallery, switching to it
+    pub gallery_search_request: RefCell<Option<String>>,
+    // switches to the duplicates tab and searches the library for them
+    pub duplicates_request: UpdateFlag,
     pub audio_device: RefCell<egui_video::AudioDevice>,
 }
 
@@ -97,8 +102,11 @@ impl App {
             namespace_colors: RefCell::new(HashMap::new()),
             disable_navbar: Arc::new(Mutex::new(vec![])),
             database_changed: Arc::new(AtomicBool::new(false)),
+            gallery_search_request: RefCell::new(None),
+            duplicates_request: Arc::new(AtomicBool::new(false)),
             // database_info_modified_flag: Arc::new(AtomicBool::new(false)),
         };
+        FolderWatcher::start(&shared_state);
         App {
             shared_state: Rc::new(shared_state),
             windows: vec![],
@@ -144,6 +152,19 @@ impl App {
         if SharedState::consume_update_flag(&self.shared_state.gallery_regenerate_flag) {
             self.generate_gallery_entries();
         }
+        let gallery_search_request = self.shared_state.gallery_search_request.borrow_mut().take();
+        if let Some(search) = gallery_search_request {
+            if let Some(gallery_ui) = self.find_window::<GalleryUI>() {
+                gallery_ui.set_search(search);
+            }
+            self.current_window = ui::icon_text(ui::constants::GALLERY_TITLE, ui::constants::GALLERY_ICON);
+        }
+        if SharedState::consume_update_flag(&self.shared_state.duplicates_request) {
+            if let Some(duplicates_ui) = self.find_window::<DuplicatesUI>() {
+                duplicates_ui.find_duplicates();
+            }
+            self.current_window = ui::icon_text(ui::constants::DUPLICATES_TITLE, ui::constants::DUPLICATE_ICON);
+        }
         if SharedState::consume_update_flag(&self.shared_state.database_changed) {
             self.check_database();
             if let Some(data_ui) = self.find_window::<DataUI>() {
@@ -158,6 +179,13 @@ impl App {
                 self.load_namespace_colors();
                 self.generate_gallery_entries();
                 tags::reload_tag_data(&self.shared_state.tag_data_ref);
+                // media stored before the similarity index existed is indexed once, off the ui thread
+                let toasts = Arc::clone(&self.shared_state.toasts);
+                thread::spawn(move || {
+                    if let Err(e) = data::sync_perceptual_hash_bits() {
+                        ui::toast_error_lock(&toasts, format!("failed to index perceptual hashes: {e}"));
+                    }
+                });
                 SharedState::set_update_flag(&self.shared_state.database_unlocked, true);
                 SharedState::remove_disabled_reason(&self.shared_state.disable_navbar, ui::constants::DISABLED_LABEL_LOCKED_DATABASE);
             }
@@ -0,0 +1,263 @@
+use anyhow::{anyhow, Result};
+use flate2::read::GzDecoder;
+use sevenz_rust::{Password, SevenZReader};
+use std::{
+    cell::Cell,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+};
+use tar::Archive as TarArchive;
+use unrar::Archive as RarArchive;
+use zip::ZipArchive;
+
+// a bare .gz is just a compressed file, so gzip only counts as an archive as a tarball
+pub const ARCHIVE_EXTENSIONS: [&str; 10] = ["zip", "cbz", "rar", "cbr", "7z", "cb7", "tar", "cbt", "tar.gz", "tgz"];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ArchiveFormat {
+    Zip,
+    Rar,
+    SevenZip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_string_lossy().to_lowercase();
+        if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            return Some(Self::TarGz);
+        }
+        match path.extension()?.to_string_lossy().to_lowercase().as_str() {
+            "zip" | "cbz" => Some(Self::Zip),
+            "rar" | "cbr" => Some(Self::Rar),
+            "7z" | "cb7" => Some(Self::SevenZip),
+            "tar" | "cbt" => Some(Self::Tar),
+            _ => None,
+        }
+    }
+
+    pub fn reader(&self, path: &Path) -> Box<dyn ArchiveReader> {
+        let path = path.to_path_buf();
+        match self {
+            Self::Zip => Box::new(ZipReader { path }),
+            Self::Rar => Box::new(RarReader { path }),
+            Self::SevenZip => Box::new(SevenZipReader { path }),
+            Self::Tar => Box::new(TarReader { path, is_gzipped: false }),
+            Self::TarGz => Box::new(TarReader { path, is_gzipped: true }),
+        }
+    }
+}
+
+/// The extension the importer's extension filter knows a file by, `tar.gz` rather than `gz` for gzipped tarballs
+pub fn filter_extension(path: &Path) -> Option<String> {
+    let file_name = path.file_name()?.to_string_lossy().to_lowercase();
+    if file_name.ends_with(".tar.gz") {
+        return Some(String::from("tar.gz"));
+    }
+    path.extension().map(|ext| ext.to_string_lossy().to_lowercase())
+}
+
+pub fn is_archive(path: &Path) -> bool {
+    ArchiveFormat::from_path(path).is_some()
+}
+
+pub fn open_archive(path: &Path) -> Result<Box<dyn ArchiveReader>> {
+    ArchiveFormat::from_path(path)
+        .map(|format| format.reader(path))
+        .ok_or(anyhow!("unsupported archive format: {}", path.display()))
+}
+
+pub trait ArchiveReader {
+    /// Extracts every file of the archive into `output_dir`, keeping the archive's folder structure.
+    /// `on_progress` receives the fraction (0 to 1) of the archive processed so far.
+    fn extract_all(&self, output_dir: &Path, on_progress: &mut dyn FnMut(f32)) -> Result<()>;
+}
+
+// archive member names can't be trusted, so only keep normal components (no "..", no roots)
+fn enclosed_member_path(member_name: &str) -> Option<PathBuf> {
+    let mut member_path = PathBuf::new();
+    for component in Path::new(&member_name.replace("\\", "/")).components() {
+        match component {
+            Component::Normal(part) => member_path.push(part),
+            Component::CurDir => (),
+            _ => return None,
+        }
+    }
+    if member_path.as_os_str().is_empty() {
+        None
+    } else {
+        Some(member_path)
+    }
+}
+
+fn write_member(output_dir: &Path, member_path: &Path, reader: &mut dyn Read) -> Result<()> {
+    let output_path = output_dir.join(member_path);
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut output_file = File::create(&output_path)?;
+    io::copy(reader, &mut output_file)?;
+    Ok(())
+}
+
+struct ZipReader {
+    path: PathBuf,
+}
+
+impl ArchiveReader for ZipReader {
+    fn extract_all(&self, output_dir: &Path, on_progress: &mut dyn FnMut(f32)) -> Result<()> {
+        let mut archive = ZipArchive::new(File::open(&self.path)?)?;
+        for i in 0..archive.len() {
+            on_progress(i as f32 / archive.len() as f32);
+            let mut inner_file = archive.by_index(i)?;
+            if inner_file.is_dir() {
+                continue;
+            }
+            if let Some(member_path) = inner_file.enclosed_name().map(|p| p.to_path_buf()) {
+                write_member(output_dir, &member_path, &mut inner_file)?;
+            }
+        }
+        on_progress(1.);
+        Ok(())
+    }
+}
+
+struct RarReader {
+    path: PathBuf,
+}
+
+impl ArchiveReader for RarReader {
+    fn extract_all(&self, output_dir: &Path, on_progress: &mut dyn FnMut(f32)) -> Result<()> {
+        let member_count = RarArchive::new(&self.path).open_for_listing()?.count();
+        let mut archive = RarArchive::new(&self.path).open_for_processing()?;
+        let mut i = 0;
+        while let Some(header) = archive.read_header()? {
+            on_progress(i as f32 / member_count.max(1) as f32);
+            i += 1;
+            let is_enclosed = enclosed_member_path(&header.entry().filename.to_string_lossy()).is_some();
+            archive = if header.entry().is_file() && is_enclosed {
+                header.extract_with_base(output_dir)?
+            } else {
+                header.skip()?
+            };
+        }
+        on_progress(1.);
+        Ok(())
+    }
+}
+
+struct SevenZipReader {
+    path: PathBuf,
+}
+
+impl ArchiveReader for SevenZipReader {
+    fn extract_all(&self, output_dir: &Path, on_progress: &mut dyn FnMut(f32)) -> Result<()> {
+        let mut archive = SevenZReader::open(&self.path, Password::empty())?;
+        let member_count = archive.archive().files.len();
+        let mut i = 0;
+        archive.for_each_entries(|entry, reader| {
+            on_progress(i as f32 / member_count.max(1) as f32);
+            i += 1;
+            match enclosed_member_path(entry.name()).filter(|_| !entry.is_directory()) {
+                Some(member_path) => write_member(output_dir, &member_path, reader).map_err(|e| sevenz_rust::Error::other(e.to_string()))?,
+                // the members of a solid block are read one after another from the same stream, so a skipped member
+                // still has to be read through or the next one starts on its bytes
+                None => {
+                    io::copy(reader, &mut io::sink()).map_err(sevenz_rust::Error::io)?;
+                }
+            }
+            Ok(true)
+        })?;
+        on_progress(1.);
+        Ok(())
+    }
+}
+
+// tar archives are streamed, so progress is measured by how much of the file has been read
+struct ProgressReader<R: Read> {
+    inner: R,
+    bytes_read: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read_count = self.inner.read(buf)?;
+        self.bytes_read.set(self.bytes_read.get() + read_count as u64);
+        Ok(read_count)
+    }
+}
+
+struct TarReader {
+    path: PathBuf,
+    is_gzipped: bool,
+}
+
+impl ArchiveReader for TarReader {
+    fn extract_all(&self, output_dir: &Path, on_progress: &mut dyn FnMut(f32)) -> Result<()> {
+        let file = File::open(&self.path)?;
+        let file_size = file.metadata()?.len().max(1);
+        let bytes_read = Rc::new(Cell::new(0));
+        let progress_reader = ProgressReader {
+            inner: file,
+            bytes_read: Rc::clone(&bytes_read),
+        };
+        let reader: Box<dyn Read> = if self.is_gzipped {
+            Box::new(GzDecoder::new(progress_reader))
+        } else {
+            Box::new(progress_reader)
+        };
+        let mut archive = TarArchive::new(reader);
+        for entry in archive.entries()? {
+            on_progress(bytes_read.get() as f32 / file_size as f32);
+            let mut entry = entry?;
+            if entry.header().entry_type().is_file() {
+                // unpack_in refuses paths that would escape output_dir
+                entry.unpack_in(output_dir)?;
+            }
+        }
+        on_progress(1.);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sevenz_rust::{SevenZArchiveEntry, SevenZWriter, SeqReader, SourceReader};
+
+    #[test]
+    fn solid_seven_zip_skips_escaping_members_without_shifting_the_rest() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive_path = temp_dir.path().join("solid.7z");
+        let members = [
+            ("first.txt", "first member"),
+            ("../escaped.txt", "escaped member"),
+            ("last.txt", "last member"),
+        ];
+        let mut writer = SevenZWriter::create(&archive_path).unwrap();
+        let entries = members
+            .iter()
+            .map(|(name, _)| {
+                let mut entry = SevenZArchiveEntry::new();
+                entry.name = name.to_string();
+                entry.has_stream = true;
+                entry
+            })
+            .collect();
+        let readers = members.iter().map(|(_, contents)| SourceReader::from(contents.as_bytes())).collect();
+        // every member in one block, so they share a single stream
+        writer.push_archive_entries(entries, SeqReader::new(readers)).unwrap();
+        writer.finish().unwrap();
+
+        let output_dir = temp_dir.path().join("output");
+        fs::create_dir(&output_dir).unwrap();
+        open_archive(&archive_path).unwrap().extract_all(&output_dir, &mut |_| ()).unwrap();
+        assert_eq!(fs::read_to_string(output_dir.join("first.txt")).unwrap(), "first member");
+        assert_eq!(fs::read_to_string(output_dir.join("last.txt")).unwrap(), "last member");
+        assert!(!output_dir.join("escaped.txt").exists());
+        assert!(!temp_dir.path().join("escaped.txt").exists());
+    }
+}
@@ -13,6 +13,9 @@ use serde::{
 };
 use std::{fs, marker::PhantomData, path::PathBuf, sync::Arc};
 
+use crate::data::{DuplicateAction, ImportationStatus};
+use crate::formats;
+use crate::import;
 use crate::ui;
 
 // use crate::tags::tags::Namespace;
@@ -77,6 +80,181 @@ pub struct General {
     pub gallery_base_search: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Import {
+    // optional regex picking the sequence number out of a file's path, the first capture group is used if there is one
+    pub sequence_regex: String,
+    // default for import sessions and what the watcher uses
+    pub duplicate_action: DuplicateAction,
+    // look for similar images in the library right after scanning
+    pub near_duplicate_check: bool,
+    // how many bits the perceptual hashes may differ by to count as a near duplicate
+    pub near_duplicate_distance: u32,
+    pub watch_enabled: bool,
+    pub watch_folders: Vec<String>,
+    pub watch_interval: u64,
+    pub watch_organize: bool,
+    pub watch_extensions: String,
+    // skip files whose hash was deleted from the library before
+    pub skip_deleted: bool,
+    // how the folders files are in turn into pools or tags
+    pub pool_mapping: PoolMapping,
+    // with PoolMapping::Depth, how many folders down from the scanned folder the pools are
+    pub pool_depth: usize,
+    // with PoolMapping::Tags, the namespace for each depth of folder, separated by spaces
+    pub folder_tag_namespaces: String,
+    // files and folders starting with a dot (or marked hidden on windows) are skipped unless this is set
+    pub scan_hidden_files: bool,
+    // the groups the importer's extension filter offers, extensions without a known format are skipped
+    pub extension_groups: Vec<ExtensionGroup>,
+    // what happens to the original files once they're imported, per outcome
+    pub organize_imported: OrganizeRule,
+    pub organize_duplicate: OrganizeRule,
+    pub organize_failed: OrganizeRule,
+    pub rules: Vec<ImportRule>,
+}
+
+impl Import {
+    /// The organize rule for a finished import, none while it's still pending
+    pub fn organize_rule(&self, status: &ImportationStatus) -> Option<&OrganizeRule> {
+        match status {
+            ImportationStatus::Pending => None,
+            ImportationStatus::Success => Some(&self.organize_imported),
+            ImportationStatus::Duplicate(_) | ImportationStatus::PreviouslyDeleted => Some(&self.organize_duplicate),
+            ImportationStatus::Fail(_) => Some(&self.organize_failed),
+        }
+    }
+
+    // folders files get moved into aren't scanned again
+    pub fn is_organize_dir(&self, dir: &std::path::Path) -> bool {
+        [&self.organize_imported, &self.organize_duplicate, &self.organize_failed]
+            .iter()
+            .any(|rule| rule.action == OrganizeAction::Move && !rule.move_to.is_empty() && dir.ends_with(&rule.move_to))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum PoolMapping {
+    // every folder at pool_depth pools all the files under it, depth 1 being the folders directly in the scanned folder
+    Depth,
+    // every folder pools just the files directly in it
+    Leaf,
+    // no pools, each folder's name becomes a tag on the files under it
+    Tags,
+}
+
+impl PoolMapping {
+    pub const ALL: [PoolMapping; 3] = [PoolMapping::Depth, PoolMapping::Leaf, PoolMapping::Tags];
+}
+
+impl std::fmt::Display for PoolMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PoolMapping::Depth => write!(f, "pool at depth"),
+            PoolMapping::Leaf => write!(f, "pool per folder"),
+            PoolMapping::Tags => write!(f, "folder tags"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum OrganizeAction {
+    Leave,
+    Move,
+    Trash,
+}
+
+impl OrganizeAction {
+    pub const ALL: [OrganizeAction; 3] = [OrganizeAction::Leave, OrganizeAction::Move, OrganizeAction::Trash];
+}
+
+impl std::fmt::Display for OrganizeAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OrganizeAction::Leave => write!(f, "leave"),
+            OrganizeAction::Move => write!(f, "move"),
+            OrganizeAction::Trash => write!(f, "trash"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct OrganizeRule {
+    pub action: OrganizeAction,
+    // relative paths are resolved against the scanned folder, and files keep their own relative path underneath
+    pub move_to: String,
+}
+
+impl OrganizeRule {
+    pub fn move_to(move_to: &str) -> Self {
+        Self {
+            action: OrganizeAction::Move,
+            move_to: move_to.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ExtensionGroup {
+    pub name: String,
+    pub extensions: String,
+}
+
+impl ExtensionGroup {
+    pub fn extension_list(&self) -> Vec<String> {
+        parse_extensions(&self.extensions)
+    }
+}
+
+// whitespace separated, with or without the leading dot
+fn parse_extensions(extensions: &str) -> Vec<String> {
+    extensions
+        .split_whitespace()
+        .map(|ext| ext.trim_start_matches('.').to_lowercase())
+        .collect()
+}
+
+// empty conditions are ignored, every set condition has to match for the rule to fire
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct ImportRule {
+    pub name: String,
+    pub is_enabled: bool,
+
+    pub path_regex: String,
+    pub file_name_regex: String,
+    pub archive_name_regex: String,
+    pub mime_prefix: String,
+    pub min_width: u32,
+    pub min_height: u32,
+    pub max_width: u32,
+    pub max_height: u32,
+
+    pub add_tags: String,
+    pub set_score: Option<i64>,
+    pub set_bookmark: Option<bool>,
+    pub assign_pool: String,
+}
+
+impl ImportRule {
+    pub fn has_dimension_conditions(&self) -> bool {
+        self.min_width > 0 || self.min_height > 0 || self.max_width > 0 || self.max_height > 0
+    }
+}
+
+impl Import {
+    // the landing folder is always watched, extra folders are relative to the root path unless absolute
+    pub fn watched_folders(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut folders = vec![path.landing()?];
+        for folder in &self.watch_folders {
+            folders.push(path.absolutize_path(folder)?);
+        }
+        Ok(folders)
+    }
+    pub fn watch_extension_filter(&self) -> Vec<String> {
+        parse_extensions(&self.watch_extensions)
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Misc {
     pub entry_short_id_length: usize,
@@ -92,6 +270,7 @@ pub struct Themes {
 pub struct Config {
     pub path: Path,
     pub general: General,
+    pub import: Import,
     pub misc: Misc,
     pub ui: Ui,
     pub themes: Themes,
@@ -341,6 +520,33 @@ impl Default for Config {
                 entry_max_score: 5,
                 gallery_base_search: Some(String::from("independant=true limit=5000")),
             },
+            import: Import {
+                sequence_regex: String::new(),
+                duplicate_action: DuplicateAction::AddToPool,
+                near_duplicate_check: true,
+                near_duplicate_distance: 4,
+                watch_enabled: false,
+                watch_folders: vec![],
+                watch_interval: 5,
+                watch_organize: true,
+                watch_extensions: formats::media_extensions().join(" "),
+                skip_deleted: true,
+                pool_mapping: PoolMapping::Depth,
+                pool_depth: 1,
+                folder_tag_namespaces: String::new(),
+                scan_hidden_files: false,
+                extension_groups: formats::default_extension_groups()
+                    .into_iter()
+                    .map(|(name, extensions)| ExtensionGroup {
+                        name: name.to_string(),
+                        extensions: extensions.join(" "),
+                    })
+                    .collect(),
+                organize_imported: OrganizeRule::move_to(import::SUCCESS_IMPORT_DIR),
+                organize_duplicate: OrganizeRule::move_to(import::DUPLICATE_IMPORT_DIR),
+                organize_failed: OrganizeRule::move_to(import::FAILED_IMPORT_DIR),
+                rules: vec![],
+            },
             misc: Misc { entry_short_id_length: 6 },
             ui: Ui {
                 gallery_thumbnail_size: 100,
@@ -5,7 +5,11 @@ use crate::tags::TagLinkType;
 use crate::ui::color32_from_hex;
 use crate::ui::color32_to_hex;
 use crate::ui::gallery_ui::EntrySearch;
+use crate::formats;
+use crate::formats::Decoder;
+use crate::similarity::{self, PerceptualHash, SimilarityIndex};
 use crate::ui::preview_ui::MediaPreview;
+use crate::util;
 
 use super::ui;
 use super::Config;
@@ -16,6 +20,7 @@ use egui::Color32;
 
 use egui_video::Player;
 
+use image::DynamicImage;
 use image::RgbaImage;
 use image::{imageops, ImageBuffer, Rgba};
 use image_hasher::{HashAlg, HasherConfig};
@@ -28,10 +33,12 @@ use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
 use rand::distributions::DistString;
 use rusqlite::ErrorCode;
+use rusqlite::OptionalExtension;
 use rusqlite::Row;
 
 use rusqlite::{params, Connection};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fmt::Display;
 
@@ -41,6 +48,7 @@ use parking_lot::Mutex;
 use std::io::Cursor;
 use std::mem::discriminant;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -127,6 +135,7 @@ pub fn flush_entry_info_media_links() -> Result<()> {
     let conn = initialize_database_connection()?;
     conn.execute("DROP TABLE entry_info", [])?;
     conn.execute("DROP TABLE media_links", [])?;
+    conn.execute("DROP TABLE entry_sources", [])?;
     Ok(())
 }
 pub fn flush_tag_definitions() -> Result<()> {
@@ -202,13 +211,98 @@ pub struct RegistrationForm {
     pub linking_dir: Option<String>,
     pub linking_value: Option<i32>,
     pub dir_link_map: Arc<Mutex<HashMap<String, i32>>>,
+    pub metadata: ImportationMetadata,
+    pub duplicate_action: DuplicateAction,
+    // a lower resolution copy already in the library that this media takes the place of
+    pub replaces: Option<String>,
+    // the importer session this media is registered under, if any
+    pub import_session_id: Option<i64>,
+    // the file the media was read from, for the session's report
+    pub source_path: Option<PathBuf>,
+}
+
+// tags, score, bookmark and sources applied to a media entry when it is registered
+#[derive(Debug, Clone, Default)]
+pub struct ImportationMetadata {
+    pub tags: Vec<Tag>,
+    pub score: Option<i64>,
+    pub is_bookmarked: Option<bool>,
+    pub sources: Vec<String>,
+}
+
+impl ImportationMetadata {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.score.is_none() && self.is_bookmarked.is_none() && self.sources.is_empty()
+    }
+    pub fn merge(&mut self, other: ImportationMetadata) {
+        for tag in other.tags {
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+        for source in other.sources {
+            if !self.sources.contains(&source) {
+                self.sources.push(source);
+            }
+        }
+        self.score = other.score.or(self.score);
+        self.is_bookmarked = other.is_bookmarked.or(self.is_bookmarked);
+    }
+    pub fn summary(&self) -> Option<String> {
+        let mut parts = vec![];
+        if !self.tags.is_empty() {
+            parts.push(format!(
+                "tags: {}",
+                self.tags.iter().map(|t| t.to_tagstring()).collect::<Vec<_>>().join(" ")
+            ));
+        }
+        if let Some(score) = self.score {
+            parts.push(format!("score: {score}"));
+        }
+        if let Some(is_bookmarked) = self.is_bookmarked {
+            parts.push(format!("bookmarked: {is_bookmarked}"));
+        }
+        if !self.sources.is_empty() {
+            parts.push(format!("sources: {}", self.sources.join(" ")));
+        }
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join("\n"))
+        }
+    }
+}
+
+/// What to do with a file that is already in the database when it is imported again
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum DuplicateAction {
+    Skip,
+    AddToPool,
+    MergeTags,
+}
+
+impl DuplicateAction {
+    pub const ALL: [DuplicateAction; 3] = [DuplicateAction::Skip, DuplicateAction::AddToPool, DuplicateAction::MergeTags];
+}
+
+impl fmt::Display for DuplicateAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DuplicateAction::Skip => write!(f, "skip"),
+            DuplicateAction::AddToPool => write!(f, "add to new pool"),
+            DuplicateAction::MergeTags => write!(f, "merge tags"),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum ImportationStatus {
     Pending,
     Success,
-    Duplicate,
+    // holds what was actually done with the existing entry
+    Duplicate(DuplicateAction),
+    // deleted from the library before, and skipped for it
+    PreviouslyDeleted,
     Fail(anyhow::Error),
 }
 
@@ -218,6 +312,58 @@ impl PartialEq for ImportationStatus {
     }
 }
 
+// errors can't be cloned, so a cloned failure only keeps its message
+impl Clone for ImportationStatus {
+    fn clone(&self) -> Self {
+        match self {
+            ImportationStatus::Pending => ImportationStatus::Pending,
+            ImportationStatus::Success => ImportationStatus::Success,
+            ImportationStatus::Duplicate(duplicate_action) => ImportationStatus::Duplicate(*duplicate_action),
+            ImportationStatus::PreviouslyDeleted => ImportationStatus::PreviouslyDeleted,
+            ImportationStatus::Fail(error) => ImportationStatus::Fail(anyhow::Error::msg(error.to_string())),
+        }
+    }
+}
+
+/// An import run saved to the database, so that it can be resumed or reviewed after a crash or restart
+pub struct ImportSession {
+    pub session_id: i64,
+    pub scan_dir: PathBuf,
+    pub date_started: i64,
+    pub files: Vec<ImportSessionFile>,
+    pub dir_link_map: HashMap<String, i32>,
+}
+
+#[derive(Clone)]
+pub struct ImportSessionRecord {
+    pub session_id: i64,
+    pub scan_dir: PathBuf,
+    pub date_started: i64,
+    pub is_finished: bool,
+    pub imported_count: i64,
+    pub duplicate_count: i64,
+    pub failed_count: i64,
+    // media and pools created by the session that are still in the database
+    pub entry_count: i64,
+}
+
+// one file of an import session's report, the hash and pool are missing if it never reached the database
+#[derive(Clone, Serialize)]
+pub struct ImportReportRow {
+    pub path: String,
+    pub outcome: String,
+    pub hash: Option<String>,
+    pub pool: Option<i32>,
+    pub error: Option<String>,
+}
+
+pub struct ImportSessionFile {
+    pub path: PathBuf,
+    pub is_selected: bool,
+    // none if the file was never marked for import
+    pub status: Option<ImportationStatus>,
+}
+
 #[derive(Clone, PartialEq, Debug, Hash)]
 pub enum EntryId {
     MediaEntry(String),
@@ -293,9 +439,7 @@ impl EntryInfo {
     }
     pub fn is_movie(&self) -> bool {
         if let EntryInfo::MediaEntry(media_info) = self {
-            if vec!["video/webm", "video/mp4", "image/gif"].contains(&media_info.mime.as_str()) {
-                return true;
-            }
+            return formats::is_video_mime(&media_info.mime);
         }
         false
     }
@@ -339,6 +483,11 @@ impl EntryInfo {
                 return false;
             }
         }
+        if let Some(import_session_id) = search.import_session_id {
+            if self.details().import_session_id != Some(import_session_id) {
+                return false;
+            }
+        }
         for tags in &search.not_relations {
             if !self.details().not_includes_any_tags(tags) {
                 return false;
@@ -396,6 +545,7 @@ pub struct EntryDetails {
     pub score: i64,
     pub is_bookmarked: bool,
     pub is_independant: bool,
+    pub import_session_id: Option<i64>,
 }
 
 impl PartialEq for EntryDetails {
@@ -436,6 +586,14 @@ impl EntryDetails {
 
 use egui_video::Streamer;
 
+fn video_frame_to_image(player: &Player, frame_pixels: &[Color32]) -> Result<RgbaImage> {
+    let pixels = frame_pixels
+        .iter()
+        .flat_map(|c32| [c32.r(), c32.g(), c32.b(), c32.a()])
+        .collect::<Vec<u8>>();
+    RgbaImage::from_raw(player.width, player.height, pixels).context("failed to make image")
+}
+
 // todo: move stuff out of struct
 pub fn generate_media_thumbnail(image_data: &[u8], is_movie: bool) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
     let thumbnail_size = Config::global().ui.thumbnail_resolution as u32;
@@ -443,12 +601,7 @@ pub fn generate_media_thumbnail(image_data: &[u8], is_movie: bool) -> Result<Ima
         let ctx = egui::Context::default();
         let player = Player::new_from_bytes(&ctx, image_data)?;
         let next_frame = player.video_streamer.lock().recieve_next_packet_until_frame()?;
-        let pixels = next_frame
-            .pixels
-            .iter()
-            .flat_map(|c32| [c32.r(), c32.g(), c32.b(), c32.a()])
-            .collect::<Vec<u8>>();
-        RgbaImage::from_raw(player.width, player.height, pixels).context("failed to make image")?
+        video_frame_to_image(&player, &next_frame.pixels)?
     } else {
         image::load_from_memory(image_data)?.to_rgba8()
     };
@@ -695,6 +848,131 @@ fn setup_databaste_with_conn(conn: &Connection) -> Result<()> {
             )",
         [],
     )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS entry_sources (
+                hash TEXT,
+                link_id INTEGER,
+                source TEXT,
+                UNIQUE (hash, link_id, source)
+            )",
+        [],
+    )?;
+    // a row's other id is null and nulls never conflict in a unique constraint, so sources are only unique through
+    // these. repeats saved before they existed are dropped first, or creating them would fail
+    let has_source_indexes: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'index' AND name = 'entry_sources_hash_source')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_source_indexes {
+        conn.execute(
+            "DELETE FROM entry_sources WHERE rowid NOT IN (SELECT MIN(rowid) FROM entry_sources GROUP BY hash, link_id, source)",
+            [],
+        )?;
+    }
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS entry_sources_hash_source ON entry_sources (hash, source) WHERE hash IS NOT NULL",
+        [],
+    )?;
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS entry_sources_link_id_source ON entry_sources (link_id, source) WHERE link_id IS NOT NULL",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS import_sessions (
+                session_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                scan_dir TEXT,
+                date_started INTEGER,
+                is_finished INTEGER DEFAULT 0
+            )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS import_session_files (
+                session_id INTEGER NOT NULL,
+                path TEXT NOT NULL,
+                is_selected INTEGER DEFAULT 0,
+                status TEXT,
+                detail TEXT,
+                UNIQUE (session_id, path)
+            )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS import_session_entries (
+                session_id INTEGER NOT NULL,
+                hash TEXT,
+                link_id INTEGER,
+                UNIQUE (session_id, hash, link_id)
+            )",
+        [],
+    )?;
+    // every entry loaded looks up the session it came from
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS import_session_entries_hash ON import_session_entries (hash)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS import_session_entries_link_id ON import_session_entries (link_id)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS import_session_results (
+                session_id INTEGER NOT NULL,
+                path TEXT NOT NULL,
+                hash TEXT,
+                link_id INTEGER,
+                UNIQUE (session_id, path)
+            )",
+        [],
+    )?;
+
+    // perceptual hashes as integers for the similarity index, one row per image or per sampled video frame
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS perceptual_hash_bits (
+                hash TEXT NOT NULL,
+                frame INTEGER NOT NULL,
+                is_video INTEGER NOT NULL,
+                bits INTEGER NOT NULL,
+                UNIQUE (hash, frame)
+            )",
+        [],
+    )?;
+
+    // pairs of similar media marked as not duplicates, hash is always the lesser of the two
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS dismissed_duplicates (
+                hash TEXT NOT NULL,
+                other_hash TEXT NOT NULL,
+                date_dismissed INTEGER NOT NULL,
+                UNIQUE (hash, other_hash)
+            )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS deleted_hashes (
+                hash TEXT PRIMARY KEY,
+                date_deleted INTEGER NOT NULL,
+                reason TEXT
+            )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS import_session_links (
+                session_id INTEGER NOT NULL,
+                linking_dir TEXT NOT NULL,
+                link_id INTEGER,
+                UNIQUE (session_id, linking_dir)
+            )",
+        [],
+    )?;
     Ok(())
 }
 
@@ -766,6 +1044,17 @@ pub fn get_media_bytes_with_conn(conn: &Connection, hash: &String) -> Result<Vec
     Ok(bytes)
 }
 
+/// Checks that the database holds an intact copy of `original_bytes`: the stored bytes have to exist
+/// under the original's hash and hash back to it
+pub fn verify_stored_media(original_bytes: &[u8]) -> Result<()> {
+    let hash = sha256::digest(original_bytes);
+    let stored_bytes = get_media_bytes(&hash).with_context(|| format!("{} isn't in the database", ui::pretty_media_id(&hash)))?;
+    if sha256::digest(stored_bytes.as_slice()) != hash {
+        return Err(anyhow!("the stored copy of {} doesn't match its hash", ui::pretty_media_id(&hash)));
+    }
+    Ok(())
+}
+
 fn construct_entry_info_with_row(entry_id: &EntryId, row: &Row) -> Result<EntryInfo> {
     let details = EntryDetails {
         id: entry_id.clone(),
@@ -775,6 +1064,7 @@ fn construct_entry_info_with_row(entry_id: &EntryId, row: &Row) -> Result<EntryI
         is_independant: row.get("is_independant")?,
         size: row.get("size")?,
         tags: vec![],
+        import_session_id: None,
     };
 
     let entry_info = match entry_id {
@@ -805,6 +1095,28 @@ fn fill_entry_info_tags_with_conn(conn: &Connection, entry_info: &mut EntryInfo)
     Ok(())
 }
 
+fn fill_import_session_with_conn(conn: &Connection, entry_info: &mut EntryInfo) -> Result<()> {
+    // one column per query, so each can use its index
+    let import_session_id = match entry_info.entry_id() {
+        EntryId::MediaEntry(hash) => conn
+            .query_row(
+                "SELECT session_id FROM import_session_entries WHERE hash = ?1 ORDER BY session_id DESC LIMIT 1",
+                params![hash],
+                |row| row.get(0),
+            )
+            .optional()?,
+        EntryId::PoolEntry(link_id) => conn
+            .query_row(
+                "SELECT session_id FROM import_session_entries WHERE link_id = ?1 ORDER BY session_id DESC LIMIT 1",
+                params![link_id],
+                |row| row.get(0),
+            )
+            .optional()?,
+    };
+    entry_info.details_mut().import_session_id = import_session_id;
+    Ok(())
+}
+
 fn fill_media_info_wth_conn(conn: &Connection, entry_info: &mut EntryInfo) -> Result<()> {
     if let EntryInfo::MediaEntry(media_info) = entry_info {
         media_info.links = get_media_links_of_hash_with_conn(&conn, media_info.details.id.as_media_entry_id().unwrap())?;
@@ -868,6 +1180,7 @@ fn get_all_entry_info_with_conn(conn: &Connection) -> Result<Vec<EntryInfo>> {
             let entry_id = entry_info_row_to_id(row)?;
             let mut entry_info = construct_entry_info_with_row(&entry_id, row).map_err(|_| GENERIC_RUSQLITE_ERROR)?;
             fill_entry_info_tags_with_conn(&conn, &mut entry_info).map_err(|_| GENERIC_RUSQLITE_ERROR)?;
+            fill_import_session_with_conn(&conn, &mut entry_info).map_err(|_| GENERIC_RUSQLITE_ERROR)?;
             fill_media_info_wth_conn(&conn, &mut entry_info).map_err(|_| GENERIC_RUSQLITE_ERROR)?;
             fill_pool_info_with_conn(&conn, &mut entry_info).map_err(|_| GENERIC_RUSQLITE_ERROR)?;
             Ok(entry_info)
@@ -891,6 +1204,7 @@ fn get_entry_info_with_conn(conn: &Connection, entry_id: &EntryId) -> Result<Ent
     })?;
 
     fill_entry_info_tags_with_conn(&conn, &mut entry_info)?;
+    fill_import_session_with_conn(&conn, &mut entry_info)?;
     fill_media_info_wth_conn(&conn, &mut entry_info)?;
     fill_pool_info_with_conn(&conn, &mut entry_info)?;
 
@@ -909,6 +1223,10 @@ pub fn set_media_link_values_in_order(link_id: &i32, hashes: Vec<String>) -> Res
 
 pub fn set_score(entry_id: &EntryId, new_score: i64) -> Result<()> {
     let conn = initialize_database_connection()?;
+    set_score_with_conn(&conn, entry_id, new_score)
+}
+
+fn set_score_with_conn(conn: &Connection, entry_id: &EntryId, new_score: i64) -> Result<()> {
     match entry_id {
         EntryId::MediaEntry(hash) => conn.execute("UPDATE entry_info SET score = ?1 WHERE hash = ?2", params![new_score, hash])?,
         EntryId::PoolEntry(link_id) => conn.execute("UPDATE entry_info SET score = ?1 WHERE link_id = ?2", params![new_score, link_id])?,
@@ -931,6 +1249,10 @@ pub fn set_independance_with_conn(conn: &Connection, hash: &String, new_state: b
 
 pub fn set_bookmark(entry_id: &EntryId, new_state: bool) -> Result<()> {
     let conn = initialize_database_connection()?;
+    set_bookmark_with_conn(&conn, entry_id, new_state)
+}
+
+fn set_bookmark_with_conn(conn: &Connection, entry_id: &EntryId, new_state: bool) -> Result<()> {
     match entry_id {
         EntryId::MediaEntry(hash) => conn.execute("UPDATE entry_info SET is_bookmarked = ?1 WHERE hash = ?2", params![new_state, hash])?,
         EntryId::PoolEntry(link_id) => conn.execute("UPDATE entry_info SET is_bookmarked = ?1 WHERE link_id = ?2", params![new_state, link_id])?,
@@ -954,10 +1276,16 @@ fn delete_entry_with_conn(conn: &Connection, entry_id: &EntryId) -> Result<()> {
             conn.execute("DELETE FROM entry_tags WHERE hash = ?1", params![hash])?;
             conn.execute("DELETE FROM media_links WHERE hash = ?1", params![hash])?;
             conn.execute("DELETE FROM thumbnail_cache WHERE hash = ?1", params![hash])?;
+            conn.execute("DELETE FROM entry_sources WHERE hash = ?1", params![hash])?;
+            conn.execute("DELETE FROM import_session_entries WHERE hash = ?1", params![hash])?;
+            set_perceptual_hash_bits_with_conn(conn, hash, None)?;
+            conn.execute("DELETE FROM dismissed_duplicates WHERE hash = ?1 OR other_hash = ?1", params![hash])?;
         }
         EntryId::PoolEntry(link_id) => {
             conn.execute("DELETE FROM entry_info WHERE link_id = ?1", params![link_id])?;
             conn.execute("DELETE FROM entry_tags WHERE link_id = ?1", params![link_id])?;
+            conn.execute("DELETE FROM entry_sources WHERE link_id = ?1", params![link_id])?;
+            conn.execute("DELETE FROM import_session_entries WHERE link_id = ?1", params![link_id])?;
             conn.execute("DELETE FROM thumbnail_cache WHERE link_id = ?1", params![link_id])?;
             let hashes_of_link = get_hashes_of_media_link_with_conn(conn, link_id)?;
             conn.execute("DELETE FROM media_links WHERE link_id = ?1", params![link_id])?;
@@ -981,8 +1309,12 @@ fn delete_entry_with_conn(conn: &Connection, entry_id: &EntryId) -> Result<()> {
 pub fn delete_entry(entry_id: &EntryId) -> Result<()> {
     let mut conn = initialize_database_connection()?;
     let tx = conn.transaction()?;
+    if let EntryId::MediaEntry(hash) = entry_id {
+        record_deleted_hash_with_conn(&tx, hash, None)?;
+    }
     delete_entry_with_conn(&tx, entry_id)?;
     tx.commit()?;
+    invalidate_similarity_index();
     Ok(())
 }
 
@@ -990,10 +1322,62 @@ pub fn delete_link_and_linked(link_id: &i32) -> Result<()> {
     let mut conn = initialize_database_connection()?;
     let tx = conn.transaction()?;
     for hash in get_hashes_of_media_link_with_conn(&tx, link_id)? {
+        record_deleted_hash_with_conn(&tx, &hash, None)?;
         delete_entry_with_conn(&tx, &EntryId::MediaEntry(hash))?;
     }
     delete_entry_with_conn(&tx, &EntryId::PoolEntry(*link_id))?;
     tx.commit()?;
+    invalidate_similarity_index();
+    Ok(())
+}
+
+/// A media hash deleted from the library, remembered so importing the same file again can be skipped
+#[derive(Clone)]
+pub struct DeletedHash {
+    pub hash: String,
+    pub date_deleted: i64,
+    pub reason: Option<String>,
+}
+
+fn record_deleted_hash_with_conn(conn: &Connection, hash: &String, reason: Option<&str>) -> Result<()> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    conn.execute(
+        "INSERT OR REPLACE INTO deleted_hashes (hash, date_deleted, reason) VALUES (?1, ?2, ?3)",
+        params![hash, timestamp, reason],
+    )?;
+    Ok(())
+}
+
+fn is_hash_deleted_with_conn(conn: &Connection, hash: &String) -> Result<bool> {
+    let mut statement = conn.prepare("SELECT 1 FROM deleted_hashes WHERE hash = ?1")?;
+    Ok(statement.exists(params![hash])?)
+}
+
+pub fn get_deleted_hashes() -> Result<Vec<DeletedHash>> {
+    let conn = initialize_database_connection()?;
+    let mut statement = conn.prepare("SELECT hash, date_deleted, reason FROM deleted_hashes ORDER BY date_deleted DESC")?;
+    let deleted_hashes = statement
+        .query_map([], |row| {
+            Ok(DeletedHash {
+                hash: row.get(0)?,
+                date_deleted: row.get(1)?,
+                reason: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(deleted_hashes)
+}
+
+// the hash can be imported again afterwards
+pub fn forget_deleted_hash(hash: &String) -> Result<()> {
+    let conn = initialize_database_connection()?;
+    conn.execute("DELETE FROM deleted_hashes WHERE hash = ?1", params![hash])?;
+    Ok(())
+}
+
+pub fn clear_deleted_hashes() -> Result<()> {
+    let conn = initialize_database_connection()?;
+    conn.execute("DELETE FROM deleted_hashes", [])?;
     Ok(())
 }
 
@@ -1113,8 +1497,12 @@ pub fn filter_to_unknown_tags(tags: &Vec<Tag>) -> Result<Vec<Tag>> {
 }
 
 fn set_tags_with_conn(conn: &Connection, entry_id: &EntryId, tags: &Vec<Tag>) -> Result<Vec<Tag>> {
-    let resolved_tags = resolve_tags_with_conn(conn, tags)?;
     clear_entry_tags_with_conn(conn, entry_id)?;
+    add_tags_with_conn(conn, entry_id, tags)
+}
+
+fn add_tags_with_conn(conn: &Connection, entry_id: &EntryId, tags: &Vec<Tag>) -> Result<Vec<Tag>> {
+    let resolved_tags = resolve_tags_with_conn(conn, tags)?;
 
     let mut insert_tag_stmt = if entry_id.is_media_entry_id() {
         conn.prepare("INSERT OR IGNORE INTO entry_tags (hash, tag) VALUES (?1, ?2)")?
@@ -1472,17 +1860,89 @@ impl FallibleSender for Vec<RegistrationForm> {
     }
 }
 
+// everything about a media that can be worked out without touching the database
+struct PreparedMedia {
+    hash: String,
+    perceptual_hash: Option<String>,
+    mime: Option<String>,
+    thumbnail: Option<Vec<u8>>,
+}
+
+fn prepare_media(bytes: &[u8], mime: Option<&mime_guess::Mime>) -> Result<PreparedMedia> {
+    // the extension's guess is only used when the contents aren't recognised
+    let mime = util::sniff_mime(bytes)
+        .or_else(|| mime.cloned())
+        .map(|mime| formats::canonical_mime(mime.essence_str()));
+    let decoder = mime.as_deref().map(formats::decoder_of_mime).unwrap_or(Decoder::Undecoded);
+    let perceptual_hash = match decoder {
+        Decoder::Image => Some(generate_perceptual_hash(&image::load_from_memory(bytes)?)),
+        // a video that can't be sampled is still imported, it just can't be matched as a near duplicate
+        Decoder::Video => generate_video_perceptual_hash(bytes).ok(),
+        Decoder::Undecoded => None,
+    };
+    let is_movie = decoder == Decoder::Video;
+    // a missing thumbnail is generated again when it's first shown, so failing here isn't fatal
+    let thumbnail = generate_media_thumbnail(bytes, is_movie).ok().and_then(|thumbnail| {
+        let mut thumbnail_bytes: Vec<u8> = Vec::new();
+        thumbnail
+            .write_to(&mut Cursor::new(&mut thumbnail_bytes), image::ImageOutputFormat::Png)
+            .ok()
+            .map(|_| thumbnail_bytes)
+    });
+    Ok(PreparedMedia {
+        hash: sha256::digest(bytes),
+        perceptual_hash,
+        mime,
+        thumbnail,
+    })
+}
+
+// hashing and decoding is spread over every core, results come back in the same order as the forms
+fn prepare_media_with_workers(reg_forms: &Vec<RegistrationForm>) -> Vec<Result<PreparedMedia>> {
+    let jobs = reg_forms
+        .iter()
+        .map(|reg_form| (Arc::clone(&reg_form.bytes), reg_form.mimetype.first()))
+        .collect::<Vec<_>>();
+    util::map_with_workers(jobs, |(bytes, mime)| prepare_media(&bytes, mime.as_ref()))
+        .into_iter()
+        .map(|result| result.unwrap_or(Err(anyhow!("media preparation worker failed"))))
+        .collect()
+}
+
 pub fn register_media_with_forms(reg_forms: Vec<RegistrationForm>) -> Result<()> {
     let (mut conn, reg_forms) = initialize_database_connection_with_senders(reg_forms)?;
+    let prepared_media = prepare_media_with_workers(&reg_forms);
+    // only the inserts happen on the writer
     let trans = conn.transaction()?;
 
-    for reg_form in reg_forms {
-        let status = register_media_with_conn(&trans, &reg_form);
-        reg_form.importation_result_sender.send(status);
+    let mut statuses = vec![];
+    for (reg_form, prepared_media) in reg_forms.into_iter().zip(prepared_media) {
+        let hash = prepared_media.as_ref().ok().map(|prepared_media| prepared_media.hash.clone());
+        let status = match prepared_media {
+            Ok(prepared_media) => register_media_with_conn(&trans, &reg_form, &prepared_media),
+            Err(error) => ImportationStatus::Fail(error),
+        };
+        // the media is registered either way, a missing result only leaves its hash and pool out of the report
+        let _ = add_import_session_result_with_conn(&trans, &reg_form, hash.as_ref(), &status);
+        statuses.push((reg_form.importation_result_sender, status));
     }
 
-    trans.commit()?;
-    Ok(())
+    // statuses only go out once the batch is saved, they're what lets the originals be organized away
+    match trans.commit() {
+        Ok(()) => {
+            invalidate_similarity_index();
+            for (sender, status) in statuses {
+                sender.send(status);
+            }
+            Ok(())
+        }
+        Err(e) => {
+            for (sender, _status) in statuses {
+                sender.send(ImportationStatus::Fail(anyhow!("the import batch failed to save: {e}")));
+            }
+            Err(e.into())
+        }
+    }
 }
 
 /* FIXME: why fails?
@@ -1506,27 +1966,19 @@ delegate_to_conn_pool(move || {
 
 */
 
-fn register_media_with_conn(conn: &Connection, reg_form: &RegistrationForm) -> ImportationStatus {
-    let register = || -> Result<ImportationStatus> {
-        let hasher_config = HasherConfig::new().hash_alg(HashAlg::DoubleGradient);
-        let hasher = hasher_config.to_hasher();
-        let mut perceptual_hash: Option<String> = None;
-        if let Some(mime) = reg_form.mimetype.first() {
-            if mime.type_() == mime_guess::mime::IMAGE {
-                let image = image::load_from_memory(&reg_form.bytes)?;
-                perceptual_hash = Some(hex::encode(hasher.hash_image(&image).as_bytes()));
-            } else if mime.type_() == mime_guess::mime::APPLICATION {
-            }
-        }
-
-        let sha_hash = sha256::digest(&*reg_form.bytes as &[u8]);
+fn register_media_with_conn(conn: &Connection, reg_form: &RegistrationForm, prepared_media: &PreparedMedia) -> ImportationStatus {
+    let mut created_linking_dir = None;
+    let mut register = || -> Result<ImportationStatus> {
+        let sha_hash = &prepared_media.hash;
         let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
-        let serialized_mime = reg_form.mimetype.first().map(|mime| mime.to_string());
 
         let mut statement = conn.prepare("SELECT 1 FROM entry_info WHERE hash = ?")?;
         let exists = statement.exists(params![sha_hash])?;
         if exists {
-            return Ok(ImportationStatus::Duplicate);
+            return resolve_duplicate_with_conn(conn, reg_form, sha_hash, &mut created_linking_dir);
+        }
+        if Config::global().import.skip_deleted && is_hash_deleted_with_conn(conn, sha_hash)? {
+            return Ok(ImportationStatus::PreviouslyDeleted);
         }
 
         let insert_result = conn.execute(
@@ -1534,8 +1986,8 @@ fn register_media_with_conn(conn: &Connection, reg_form: &RegistrationForm) -> I
                         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
             params![
                 sha_hash,
-                perceptual_hash,
-                serialized_mime,
+                prepared_media.perceptual_hash,
+                prepared_media.mime,
                 timestamp,
                 reg_form.bytes.len(),
                 reg_form.linking_dir.is_none()
@@ -1545,30 +1997,32 @@ fn register_media_with_conn(conn: &Connection, reg_form: &RegistrationForm) -> I
         match insert_result {
             Ok(_) => {
                 conn.execute("INSERT INTO media_bytes (hash, bytes) VALUES (?1, ?2)", params![sha_hash, reg_form.bytes])?;
+                // it's back in the library, so it no longer counts as deleted
+                conn.execute("DELETE FROM deleted_hashes WHERE hash = ?1", params![sha_hash])?;
+                set_perceptual_hash_bits_with_conn(conn, sha_hash, prepared_media.perceptual_hash.as_deref())?;
                 if let Some(linking_dir) = &reg_form.linking_dir {
-                    let mut dir_link_map = reg_form.dir_link_map.lock();
-                    let link_id = if let Some(link_id) = dir_link_map.get(linking_dir) {
-                        *link_id
-                    } else {
-                        let next_id = create_new_link_with_conn(conn)?;
-                        dir_link_map.insert(linking_dir.clone(), next_id);
-                        next_id
-                    };
-
+                    let link_id = get_or_create_import_link_with_conn(conn, reg_form, linking_dir, &mut created_linking_dir)?;
                     conn.execute(
                         "INSERT INTO media_links (link_id, hash, value)
                             VALUES (?1, ?2, ?3)",
                         params![link_id, sha_hash, reg_form.linking_value],
                     )?;
                 }
+                apply_importation_metadata_with_conn(conn, &EntryId::MediaEntry(sha_hash.clone()), &reg_form.metadata)?;
+                add_import_session_entry_with_conn(conn, reg_form, &EntryId::MediaEntry(sha_hash.clone()))?;
+                if let Some(replaced_hash) = &reg_form.replaces {
+                    replace_media_with_conn(conn, replaced_hash, sha_hash)?;
+                }
 
-                // let _ = load_thumbnail_with_conn(conn, &EntryId::MediaEntry(sha_hash));
+                if let Some(thumbnail) = &prepared_media.thumbnail {
+                    conn.execute("INSERT INTO thumbnail_cache (hash, bytes) VALUES (?1, ?2)", params![sha_hash, thumbnail])?;
+                }
                 return Ok(ImportationStatus::Success);
             }
             Err(error) => {
                 if let rusqlite::Error::SqliteFailure(e, _) = error {
                     if e.code == rusqlite::ErrorCode::ConstraintViolation {
-                        return Ok(ImportationStatus::Duplicate);
+                        return resolve_duplicate_with_conn(conn, reg_form, sha_hash, &mut created_linking_dir);
                     }
                 }
                 return Ok(ImportationStatus::Fail(error.into()));
@@ -1576,75 +2030,580 @@ fn register_media_with_conn(conn: &Connection, reg_form: &RegistrationForm) -> I
         }
     };
 
-    match register() {
-        Ok(status) => return status,
-        Err(error) => return ImportationStatus::Fail(error),
+    // the entry is registered together with its tags, score, bookmark and sources, so a failure
+    // partway through doesn't leave behind an entry missing what was assigned to it
+    if let Err(error) = conn.execute_batch("SAVEPOINT register_media") {
+        return ImportationStatus::Fail(error.into());
+    }
+    let status = match register() {
+        Ok(status) => status,
+        Err(error) => ImportationStatus::Fail(error),
     };
+    let end_savepoint = if !matches!(status, ImportationStatus::Fail(_)) {
+        "RELEASE register_media"
+    } else {
+        if let Some(created_linking_dir) = created_linking_dir {
+            reg_form.dir_link_map.lock().remove(&created_linking_dir);
+        }
+        "ROLLBACK TO register_media; RELEASE register_media"
+    };
+    if let Err(error) = conn.execute_batch(end_savepoint) {
+        return ImportationStatus::Fail(error.into());
+    }
+    status
 }
-fn get_next_link_id_with_conn(conn: &Connection) -> Result<i32> {
-    let next_id: i32 = conn.query_row("SELECT IFNULL(MAX(link_id), 0) + 1 FROM media_links ", [], |row| row.get(0))?;
-    conn.execute("DELETE FROM entry_info WHERE link_id = ?1", params![next_id])?;
-    Ok(next_id)
-}
-fn time_now() -> Result<u64> {
-    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
-}
-fn create_new_link_with_conn(conn: &Connection) -> Result<i32> {
-    let next_id = get_next_link_id_with_conn(conn)?;
-    let timestamp = time_now()?;
+// the new media takes over the tags, sources, score, bookmark and pools of the old one, which is then deleted
+fn replace_media_with_conn(conn: &Connection, old_hash: &String, new_hash: &String) -> Result<()> {
+    // link_id is null for media rows and nulls never conflict in a unique constraint, so the kept media's own rows
+    // are checked for instead
     conn.execute(
-        "INSERT INTO entry_info (link_id, date_registered) VALUES (?1, ?2)",
-        params![next_id, timestamp],
+        "INSERT INTO entry_tags (hash, tag) SELECT DISTINCT ?2, tag FROM entry_tags AS old_tags WHERE hash = ?1
+            AND NOT EXISTS (SELECT 1 FROM entry_tags WHERE hash = ?2 AND tag = old_tags.tag)",
+        params![old_hash, new_hash],
+    )?;
+    conn.execute(
+        "INSERT INTO entry_sources (hash, source) SELECT DISTINCT ?2, source FROM entry_sources AS old_sources WHERE hash = ?1
+            AND NOT EXISTS (SELECT 1 FROM entry_sources WHERE hash = ?2 AND source = old_sources.source)",
+        params![old_hash, new_hash],
+    )?;
+    conn.execute(
+        "UPDATE entry_info SET
+            score = MAX(score, (SELECT score FROM entry_info WHERE hash = ?1)),
+            is_bookmarked = MAX(is_bookmarked, (SELECT is_bookmarked FROM entry_info WHERE hash = ?1)),
+            is_independant = MIN(is_independant, (SELECT is_independant FROM entry_info WHERE hash = ?1))
+        WHERE hash = ?2",
+        params![old_hash, new_hash],
+    )?;
+    // pools both are already in would otherwise hold the new media twice
+    conn.execute(
+        "DELETE FROM media_links WHERE hash = ?1 AND link_id IN (SELECT link_id FROM media_links WHERE hash = ?2)",
+        params![old_hash, new_hash],
     )?;
+    conn.execute("UPDATE media_links SET hash = ?2 WHERE hash = ?1", params![old_hash, new_hash])?;
+    for link_id in get_media_links_of_hash_with_conn(conn, new_hash)? {
+        conn.execute("DELETE FROM thumbnail_cache WHERE link_id = ?1", params![link_id])?;
+    }
+    record_deleted_hash_with_conn(conn, old_hash, Some("replaced by a near duplicate"))?;
+    delete_entry_with_conn(conn, &EntryId::MediaEntry(old_hash.clone()))
+}
+
+// links made while importing are shared by every file of the same linking dir
+fn get_or_create_import_link_with_conn(
+    conn: &Connection,
+    reg_form: &RegistrationForm,
+    linking_dir: &String,
+    created_linking_dir: &mut Option<String>,
+) -> Result<i32> {
+    let mut dir_link_map = reg_form.dir_link_map.lock();
+    // the map can outlive the pool, a long running folder watcher or a resumed session mustn't link to a deleted one
+    if let Some(link_id) = dir_link_map.get(linking_dir).copied() {
+        let mut statement = conn.prepare("SELECT 1 FROM entry_info WHERE link_id = ?1")?;
+        if statement.exists(params![link_id])? {
+            return Ok(link_id);
+        }
+    }
+    let next_id = create_new_link_with_conn(conn)?;
+    add_import_session_entry_with_conn(conn, reg_form, &EntryId::PoolEntry(next_id))?;
+    dir_link_map.insert(linking_dir.clone(), next_id);
+    *created_linking_dir = Some(linking_dir.clone());
     Ok(next_id)
 }
-fn load_tag_data_with_conn(conn: &Connection, tag: &Tag) -> Result<TagData> {
-    let mut count_stmt = conn.prepare("SELECT COUNT(*) FROM entry_tags WHERE tag = ?1")?;
-    let mut link_stmt = conn.prepare("SELECT * FROM tag_links WHERE from_tag = ?1 OR to_tag = ?1")?;
-    let occurances: i32 = count_stmt.query_row(params![tag.to_tagstring()], |row| Ok(row.get(0)?))?;
 
-    let link_results = link_stmt.query_map(params![tag.to_tagstring()], |row| {
-        let link_type: String = row.get(0)?;
-        Ok(TagLink {
-            link_type: TagLinkType::from(link_type),
-            from_tagstring: row.get(1)?,
-            to_tagstring: row.get(2)?,
-        })
-    })?;
+// remembers which import session created an entry, so the session can be searched for and reverted
+fn add_import_session_entry_with_conn(conn: &Connection, reg_form: &RegistrationForm, entry_id: &EntryId) -> Result<()> {
+    if let Some(session_id) = reg_form.import_session_id {
+        conn.execute(
+            "INSERT OR IGNORE INTO import_session_entries (session_id, hash, link_id) VALUES (?1, ?2, ?3)",
+            params![session_id, entry_id.as_media_entry_id(), entry_id.as_pool_entry_id()],
+        )?;
+    }
+    Ok(())
+}
 
-    let mut tag_data = TagData {
-        tag: tag.noneified(),
-        occurances,
-        links: vec![],
-    };
+// the hash and pool a file ended up as, which the session's report shows next to the file's status
+fn add_import_session_result_with_conn(
+    conn: &Connection,
+    reg_form: &RegistrationForm,
+    hash: Option<&String>,
+    status: &ImportationStatus,
+) -> Result<()> {
+    if let (Some(session_id), Some(source_path)) = (reg_form.import_session_id, reg_form.source_path.as_ref()) {
+        let is_in_pool = matches!(
+            status,
+            ImportationStatus::Success | ImportationStatus::Duplicate(DuplicateAction::AddToPool)
+        );
+        let link_id = match &reg_form.linking_dir {
+            Some(linking_dir) if is_in_pool => reg_form.dir_link_map.lock().get(linking_dir).copied(),
+            _ => None,
+        };
+        conn.execute(
+            "INSERT OR REPLACE INTO import_session_results (session_id, path, hash, link_id) VALUES (?1, ?2, ?3, ?4)",
+            params![session_id, source_path.to_string_lossy(), hash, link_id],
+        )?;
+    }
+    Ok(())
+}
 
-    for link_result in link_results {
-        if let Ok(link) = link_result {
-            tag_data.links.push(link);
+fn resolve_duplicate_with_conn(
+    conn: &Connection,
+    reg_form: &RegistrationForm,
+    hash: &String,
+    created_linking_dir: &mut Option<String>,
+) -> Result<ImportationStatus> {
+    match reg_form.duplicate_action {
+        DuplicateAction::AddToPool => {
+            if let Some(linking_dir) = &reg_form.linking_dir {
+                let link_id = get_or_create_import_link_with_conn(conn, reg_form, linking_dir, created_linking_dir)?;
+                let mut statement = conn.prepare("SELECT 1 FROM media_links WHERE link_id = ?1 AND hash = ?2")?;
+                if !statement.exists(params![link_id, hash])? {
+                    conn.execute(
+                        "INSERT INTO media_links (link_id, hash, value) VALUES (?1, ?2, ?3)",
+                        params![link_id, hash, reg_form.linking_value],
+                    )?;
+                    set_independance_with_conn(conn, hash, false)?;
+                    return Ok(ImportationStatus::Duplicate(DuplicateAction::AddToPool));
+                }
+            }
         }
+        DuplicateAction::MergeTags => {
+            if !reg_form.metadata.is_empty() {
+                apply_importation_metadata_with_conn(conn, &EntryId::MediaEntry(hash.clone()), &reg_form.metadata)?;
+                return Ok(ImportationStatus::Duplicate(DuplicateAction::MergeTags));
+            }
+        }
+        DuplicateAction::Skip => (),
     }
-    Ok(tag_data)
+    Ok(ImportationStatus::Duplicate(DuplicateAction::Skip))
 }
 
-fn set_media_link_value_with_conn(conn: &Connection, link_id: &i32, hash: &String, value: i64) -> Result<()> {
-    conn.execute(
-        "UPDATE media_links SET value = ?1 WHERE link_id = ?2 AND hash = ?3",
-        params![value, link_id, hash],
-    )?;
+fn apply_importation_metadata_with_conn(conn: &Connection, entry_id: &EntryId, metadata: &ImportationMetadata) -> Result<()> {
+    if !metadata.tags.is_empty() {
+        // tags coming from outside the database are defined on the fly
+        for tag in &metadata.tags {
+            let s_tag = tag.someified();
+            let mut statement = conn.prepare("SELECT 1 FROM tag_info WHERE name = ?1 AND namespace = ?2")?;
+            if !statement.exists(params![s_tag.name, s_tag.namespace])? {
+                register_tag_with_conn(conn, tag)?;
+            }
+        }
+        add_tags_with_conn(conn, entry_id, &metadata.tags)?;
+    }
+    if let Some(score) = metadata.score {
+        set_score_with_conn(conn, entry_id, score)?;
+    }
+    if let Some(is_bookmarked) = metadata.is_bookmarked {
+        set_bookmark_with_conn(conn, entry_id, is_bookmarked)?;
+    }
+    for source in &metadata.sources {
+        add_entry_source_with_conn(conn, entry_id, source)?;
+    }
     Ok(())
 }
 
-pub fn delete_cached_thumbnail(entry_id: &EntryId) -> Result<()> {
-    let conn = initialize_database_connection()?;
+fn add_entry_source_with_conn(conn: &Connection, entry_id: &EntryId, source: &String) -> Result<()> {
     match entry_id {
-        EntryId::MediaEntry(hash) => conn.execute("DELETE FROM thumbnail_cache WHERE hash = ?1", params![hash])?,
-        EntryId::PoolEntry(link_id) => conn.execute("DELETE FROM thumbnail_cache WHERE link_id = ?1", params![link_id])?,
+        EntryId::MediaEntry(hash) => conn.execute(
+            "INSERT OR IGNORE INTO entry_sources (hash, source) VALUES (?1, ?2)",
+            params![hash, source],
+        )?,
+        EntryId::PoolEntry(link_id) => conn.execute(
+            "INSERT OR IGNORE INTO entry_sources (link_id, source) VALUES (?1, ?2)",
+            params![link_id, source],
+        )?,
     };
-
     Ok(())
 }
 
-pub fn merge_pool_links(link_id_a: &i32, link_id_b: &i32, dest_link_id: &i32) -> Result<()> {
+pub fn get_entry_sources(entry_id: &EntryId) -> Result<Vec<String>> {
+    let conn = initialize_database_connection()?;
+    get_entry_sources_with_conn(&conn, entry_id)
+}
+
+pub fn get_entry_sources_with_conn(conn: &Connection, entry_id: &EntryId) -> Result<Vec<String>> {
+    let mut statement = match entry_id {
+        EntryId::MediaEntry(_) => conn.prepare("SELECT source FROM entry_sources WHERE hash = ?1")?,
+        EntryId::PoolEntry(_) => conn.prepare("SELECT source FROM entry_sources WHERE link_id = ?1")?,
+    };
+    let rows = match entry_id {
+        EntryId::MediaEntry(hash) => statement.query_map(params![hash], |row| row.get(0))?,
+        EntryId::PoolEntry(link_id) => statement.query_map(params![link_id], |row| row.get(0))?,
+    };
+    Ok(rows.collect::<Result<Vec<String>, _>>()?)
+}
+
+pub fn get_import_session_records() -> Result<Vec<ImportSessionRecord>> {
+    let conn = initialize_database_connection()?;
+    let mut statement = conn.prepare(
+        "SELECT session_id, scan_dir, date_started, is_finished,
+            (SELECT COUNT(*) FROM import_session_files f WHERE f.session_id = s.session_id AND f.status = 'success'),
+            (SELECT COUNT(*) FROM import_session_files f WHERE f.session_id = s.session_id AND f.status = 'duplicate'),
+            (SELECT COUNT(*) FROM import_session_files f WHERE f.session_id = s.session_id AND f.status = 'fail'),
+            (SELECT COUNT(*) FROM import_session_entries e WHERE e.session_id = s.session_id)
+        FROM import_sessions s ORDER BY session_id DESC",
+    )?;
+    let records = statement
+        .query_map([], |row| {
+            Ok(ImportSessionRecord {
+                session_id: row.get(0)?,
+                scan_dir: PathBuf::from(row.get::<_, String>(1)?),
+                date_started: row.get(2)?,
+                is_finished: row.get(3)?,
+                imported_count: row.get(4)?,
+                duplicate_count: row.get(5)?,
+                failed_count: row.get(6)?,
+                entry_count: row.get(7)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(records)
+}
+
+// every file that was selected in the session, in the order they were saved
+pub fn get_import_report(session_id: i64) -> Result<Vec<ImportReportRow>> {
+    let conn = initialize_database_connection()?;
+    let mut statement = conn.prepare(
+        "SELECT f.path, f.status, f.detail, r.hash, r.link_id FROM import_session_files f
+            LEFT JOIN import_session_results r ON r.session_id = f.session_id AND r.path = f.path
+        WHERE f.session_id = ?1 AND f.is_selected = 1 ORDER BY f.path",
+    )?;
+    let rows = statement
+        .query_map(params![session_id], |row| {
+            let status: Option<String> = row.get(1)?;
+            let detail: Option<String> = row.get(2)?;
+            let is_failure = status.as_deref() == Some("fail");
+            Ok(ImportReportRow {
+                path: row.get(0)?,
+                outcome: status.unwrap_or(String::from("not imported")),
+                hash: row.get(3)?,
+                pool: row.get(4)?,
+                error: if is_failure { detail } else { None },
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+/// Writes the report as json if `export_path` ends in .json, and as csv otherwise
+pub fn export_import_report(rows: &Vec<ImportReportRow>, export_path: &PathBuf) -> Result<()> {
+    let is_json = export_path.extension().map(|ext| ext.eq_ignore_ascii_case("json")).unwrap_or(false);
+    let contents = if is_json {
+        serde_json::to_string_pretty(rows)?
+    } else {
+        import_report_csv(rows)
+    };
+    fs::write(export_path, contents)?;
+    Ok(())
+}
+
+// paths and errors can hold commas, quotes and newlines, so those fields are quoted
+fn import_report_csv(rows: &Vec<ImportReportRow>) -> String {
+    let csv_field = |field: &str| {
+        if field.contains(&[',', '"', '\n', '\r'][..]) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    };
+    let mut contents = String::from("path,outcome,hash,pool,error\n");
+    for row in rows {
+        let fields = [
+            csv_field(&row.path),
+            csv_field(&row.outcome),
+            row.hash.clone().unwrap_or_default(),
+            row.pool.map(|pool| pool.to_string()).unwrap_or_default(),
+            csv_field(row.error.as_deref().unwrap_or_default()),
+        ];
+        contents.push_str(&fields.join(","));
+        contents.push('\n');
+    }
+    contents
+}
+
+/// Deletes every media and pool created by the import session, then the session itself.
+/// Returns the ids of the deleted entries
+pub fn revert_import_session(session_id: i64) -> Result<Vec<EntryId>> {
+    let mut conn = initialize_database_connection()?;
+    let tx = conn.transaction()?;
+    let mut entry_ids = {
+        let mut statement = tx.prepare("SELECT hash, link_id FROM import_session_entries WHERE session_id = ?1")?;
+        let entry_ids = statement
+            .query_map(params![session_id], |row| entry_info_row_to_id(row))?
+            .collect::<Result<Vec<_>, _>>()?;
+        entry_ids
+    };
+    // pools go first, so media that is also in another pool keeps the right independance
+    entry_ids.sort_by_key(|entry_id| entry_id.is_media_entry_id());
+    for entry_id in &entry_ids {
+        delete_entry_with_conn(&tx, entry_id)?;
+    }
+    for table in [
+        "import_session_entries",
+        "import_session_files",
+        "import_session_results",
+        "import_session_links",
+        "import_sessions",
+    ] {
+        tx.execute(&format!("DELETE FROM {table} WHERE session_id = ?1"), params![session_id])?;
+    }
+    tx.commit()?;
+    invalidate_similarity_index();
+    Ok(entry_ids)
+}
+
+// stored as (status, detail), where detail holds the duplicate action or the error message
+fn importation_status_to_columns(status: &ImportationStatus) -> (&'static str, Option<String>) {
+    match status {
+        ImportationStatus::Pending => ("pending", None),
+        ImportationStatus::Success => ("success", None),
+        ImportationStatus::Duplicate(duplicate_action) => ("duplicate", Some(format!("{duplicate_action:?}"))),
+        ImportationStatus::PreviouslyDeleted => ("deleted", None),
+        ImportationStatus::Fail(error) => ("fail", Some(error.to_string())),
+    }
+}
+
+fn importation_status_from_columns(status: Option<String>, detail: Option<String>) -> Option<ImportationStatus> {
+    match status.as_deref() {
+        Some("pending") => Some(ImportationStatus::Pending),
+        Some("success") => Some(ImportationStatus::Success),
+        Some("duplicate") => Some(ImportationStatus::Duplicate(match detail.as_deref() {
+            Some("AddToPool") => DuplicateAction::AddToPool,
+            Some("MergeTags") => DuplicateAction::MergeTags,
+            _ => DuplicateAction::Skip,
+        })),
+        Some("deleted") => Some(ImportationStatus::PreviouslyDeleted),
+        Some("fail") => Some(ImportationStatus::Fail(anyhow::Error::msg(detail.unwrap_or_default()))),
+        _ => None,
+    }
+}
+
+pub fn create_import_session(scan_dir: &PathBuf, files: &Vec<ImportSessionFile>) -> Result<i64> {
+    let mut conn = initialize_database_connection()?;
+    let trans = conn.transaction()?;
+    trans.execute(
+        "INSERT INTO import_sessions (scan_dir, date_started) VALUES (?1, ?2)",
+        params![scan_dir.to_string_lossy(), time_now()?],
+    )?;
+    let session_id = trans.last_insert_rowid();
+    set_import_session_files_with_conn(&trans, session_id, files)?;
+    trans.commit()?;
+    Ok(session_id)
+}
+
+/// Saves the latest state of the given files and the session's pool mapping
+pub fn update_import_session(session_id: i64, files: &Vec<ImportSessionFile>, dir_link_map: &HashMap<String, i32>) -> Result<()> {
+    let mut conn = initialize_database_connection()?;
+    let trans = conn.transaction()?;
+    set_import_session_files_with_conn(&trans, session_id, files)?;
+    for (linking_dir, link_id) in dir_link_map {
+        trans.execute(
+            "INSERT OR REPLACE INTO import_session_links (session_id, linking_dir, link_id) VALUES (?1, ?2, ?3)",
+            params![session_id, linking_dir, link_id],
+        )?;
+    }
+    trans.commit()?;
+    Ok(())
+}
+
+fn set_import_session_files_with_conn(conn: &Connection, session_id: i64, files: &Vec<ImportSessionFile>) -> Result<()> {
+    let mut statement =
+        conn.prepare("INSERT OR REPLACE INTO import_session_files (session_id, path, is_selected, status, detail) VALUES (?1, ?2, ?3, ?4, ?5)")?;
+    for file in files {
+        let (status, detail) = match file.status.as_ref().map(importation_status_to_columns) {
+            Some((status, detail)) => (Some(status), detail),
+            None => (None, None),
+        };
+        statement.execute(params![session_id, file.path.to_string_lossy(), file.is_selected, status, detail])?;
+    }
+    Ok(())
+}
+
+pub fn finish_import_session(session_id: i64) -> Result<()> {
+    let conn = initialize_database_connection()?;
+    conn.execute("UPDATE import_sessions SET is_finished = 1 WHERE session_id = ?1", params![session_id])?;
+    Ok(())
+}
+
+/// Loads the most recent import session that never finished, if there is one
+pub fn get_unfinished_import_session() -> Result<Option<ImportSession>> {
+    let conn = initialize_database_connection()?;
+    get_unfinished_import_session_with_conn(&conn)
+}
+
+fn get_unfinished_import_session_with_conn(conn: &Connection) -> Result<Option<ImportSession>> {
+    let session = conn
+        .query_row(
+            "SELECT session_id, scan_dir, date_started FROM import_sessions WHERE is_finished = 0 ORDER BY session_id DESC LIMIT 1",
+            [],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?)),
+        )
+        .optional()?;
+    let (session_id, scan_dir, date_started) = match session {
+        Some(session) => session,
+        None => return Ok(None),
+    };
+
+    let mut files_statement = conn.prepare("SELECT path, is_selected, status, detail FROM import_session_files WHERE session_id = ?1")?;
+    let files = files_statement
+        .query_map(params![session_id], |row| {
+            Ok(ImportSessionFile {
+                path: PathBuf::from(row.get::<_, String>(0)?),
+                is_selected: row.get(1)?,
+                status: importation_status_from_columns(row.get(2)?, row.get(3)?),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut links_statement = conn.prepare("SELECT linking_dir, link_id FROM import_session_links WHERE session_id = ?1")?;
+    let dir_link_map = links_statement
+        .query_map(params![session_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<HashMap<String, i32>, _>>()?;
+
+    Ok(Some(ImportSession {
+        session_id,
+        scan_dir: PathBuf::from(scan_dir),
+        date_started,
+        files,
+        dir_link_map,
+    }))
+}
+
+fn get_next_link_id_with_conn(conn: &Connection) -> Result<i32> {
+    let next_id: i32 = conn.query_row("SELECT IFNULL(MAX(link_id), 0) + 1 FROM media_links ", [], |row| row.get(0))?;
+    conn.execute("DELETE FROM entry_info WHERE link_id = ?1", params![next_id])?;
+    Ok(next_id)
+}
+fn time_now() -> Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+fn create_new_link_with_conn(conn: &Connection) -> Result<i32> {
+    let next_id = get_next_link_id_with_conn(conn)?;
+    let timestamp = time_now()?;
+    conn.execute(
+        "INSERT INTO entry_info (link_id, date_registered) VALUES (?1, ?2)",
+        params![next_id, timestamp],
+    )?;
+    Ok(next_id)
+}
+fn load_tag_data_with_conn(conn: &Connection, tag: &Tag) -> Result<TagData> {
+    let mut count_stmt = conn.prepare("SELECT COUNT(*) FROM entry_tags WHERE tag = ?1")?;
+    let mut link_stmt = conn.prepare("SELECT * FROM tag_links WHERE from_tag = ?1 OR to_tag = ?1")?;
+    let occurances: i32 = count_stmt.query_row(params![tag.to_tagstring()], |row| Ok(row.get(0)?))?;
+
+    let link_results = link_stmt.query_map(params![tag.to_tagstring()], |row| {
+        let link_type: String = row.get(0)?;
+        Ok(TagLink {
+            link_type: TagLinkType::from(link_type),
+            from_tagstring: row.get(1)?,
+            to_tagstring: row.get(2)?,
+        })
+    })?;
+
+    let mut tag_data = TagData {
+        tag: tag.noneified(),
+        occurances,
+        links: vec![],
+    };
+
+    for link_result in link_results {
+        if let Ok(link) = link_result {
+            tag_data.links.push(link);
+        }
+    }
+    Ok(tag_data)
+}
+
+fn set_media_link_value_with_conn(conn: &Connection, link_id: &i32, hash: &String, value: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE media_links SET value = ?1 WHERE link_id = ?2 AND hash = ?3",
+        params![value, link_id, hash],
+    )?;
+    Ok(())
+}
+
+// re-sniffs every stored media and fixes mime types that were taken from a misleading extension,
+// returns how many were corrected
+pub fn correct_media_mimes() -> Result<usize> {
+    let conn = initialize_database_connection()?;
+    let stored_media = {
+        let mut statement = conn.prepare("SELECT hash, mime, perceptual_hash FROM entry_info WHERE hash IS NOT NULL")?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>("hash")?,
+                row.get::<_, Option<String>>("mime")?,
+                row.get::<_, Option<String>>("perceptual_hash")?,
+            ))
+        })?;
+        let stored_media = rows.collect::<Result<Vec<_>, _>>()?;
+        stored_media
+    };
+    let mut corrected_count = 0;
+    for (hash, mime, perceptual_hash) in stored_media {
+        let bytes = get_media_bytes_with_conn(&conn, &hash)?;
+        let sniffed_mime = match util::sniff_mime(&bytes) {
+            Some(sniffed_mime) => sniffed_mime,
+            None => continue,
+        };
+        if mime.as_deref() == Some(sniffed_mime.essence_str()) {
+            continue;
+        }
+        let decoder = formats::decoder_of_mime(sniffed_mime.essence_str());
+        // a hash of the right kind doesn't change with the mime, the bytes are the same
+        let perceptual_hash = match perceptual_hash {
+            Some(p_hash) if decoder != Decoder::Undecoded && is_video_perceptual_hash(&p_hash) == (decoder == Decoder::Video) => Some(p_hash),
+            _ => generate_media_perceptual_hash(&bytes, decoder),
+        };
+        conn.execute(
+            "UPDATE entry_info SET mime = ?1, perceptual_hash = ?2 WHERE hash = ?3",
+            params![sniffed_mime.essence_str(), perceptual_hash, hash],
+        )?;
+        set_perceptual_hash_bits_with_conn(&conn, &hash, perceptual_hash.as_deref())?;
+        invalidate_similarity_index();
+        // the thumbnail may have been made as the wrong kind of media
+        conn.execute("DELETE FROM thumbnail_cache WHERE hash = ?1", params![hash])?;
+        corrected_count += 1;
+    }
+    Ok(corrected_count)
+}
+
+// gives the videos imported before they were hashed a perceptual hash, returns how many were hashed
+pub fn backfill_video_perceptual_hashes() -> Result<usize> {
+    let conn = initialize_database_connection()?;
+    let unhashed_media = {
+        let mut statement = conn.prepare("SELECT hash, mime FROM entry_info WHERE hash IS NOT NULL AND perceptual_hash IS NULL")?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, String>("hash")?, row.get::<_, Option<String>>("mime")?)))?;
+        let unhashed_media = rows.collect::<Result<Vec<_>, _>>()?;
+        unhashed_media
+    };
+    let mut hashed_count = 0;
+    for (hash, mime) in unhashed_media {
+        if !mime.map(|mime| formats::is_video_mime(&mime)).unwrap_or(false) {
+            continue;
+        }
+        // a video that can't be decoded is left for next time rather than failing the rest
+        if let Ok(perceptual_hash) = generate_video_perceptual_hash(&get_media_bytes_with_conn(&conn, &hash)?) {
+            conn.execute(
+                "UPDATE entry_info SET perceptual_hash = ?1 WHERE hash = ?2",
+                params![perceptual_hash, hash],
+            )?;
+            set_perceptual_hash_bits_with_conn(&conn, &hash, Some(&perceptual_hash))?;
+            invalidate_similarity_index();
+            hashed_count += 1;
+        }
+    }
+    Ok(hashed_count)
+}
+
+pub fn delete_cached_thumbnail(entry_id: &EntryId) -> Result<()> {
+    let conn = initialize_database_connection()?;
+    match entry_id {
+        EntryId::MediaEntry(hash) => conn.execute("DELETE FROM thumbnail_cache WHERE hash = ?1", params![hash])?,
+        EntryId::PoolEntry(link_id) => conn.execute("DELETE FROM thumbnail_cache WHERE link_id = ?1", params![link_id])?,
+    };
+
+    Ok(())
+}
+
+pub fn merge_pool_links(link_id_a: &i32, link_id_b: &i32, dest_link_id: &i32) -> Result<()> {
     let mut conn = initialize_database_connection()?;
     let tx = conn.transaction()?;
     let hashes = get_hashes_of_media_link_with_conn(&tx, link_id_a)?
@@ -1756,30 +2715,683 @@ pub fn export_entry(entry_id: &EntryId, export_path: PathBuf) -> Result<PathBuf>
     export_entry_with_conn(&conn, entry_id, export_path)
 }
 
-pub fn find_duplicates() -> Result<Vec<Vec<(EntryId, String)>>> {
-    let hamming_threshold = 2;
-    fn hamming_distance(s1: &String, s2: &String) -> usize {
-        s1.chars().zip(s2.chars()).map(|(c1, c2)| if c1 == c2 { 0 } else { 1 }).sum()
+pub const TAKEOUT_ARCHIVE_EXTENSION: &str = "takeout";
+
+// a takeout archive is just another sqlcipher database with the same tables, keyed with its own password
+fn attach_takeout_archive_with_conn(conn: &Connection, archive_path: &PathBuf, archive_key: &String) -> Result<()> {
+    let archive_conn = Connection::open(archive_path)?;
+    apply_database_key_to_conn(&archive_conn, archive_key)?;
+    if !is_connection_unlocked(&archive_conn)? {
+        return Err(anyhow!("invalid archive key or invalid archive"));
+    }
+    setup_databaste_with_conn(&archive_conn)?;
+    archive_conn.close().map_err(|_| anyhow!("failed to close archive conn"))?;
+
+    conn.execute(
+        "ATTACH DATABASE ?1 AS takeout KEY ?2",
+        params![archive_path.to_string_lossy(), archive_key],
+    )?;
+    Ok(())
+}
+
+fn export_entry_to_archive_with_conn(conn: &Connection, entry_id: &EntryId) -> Result<()> {
+    match entry_id {
+        EntryId::MediaEntry(hash) => {
+            let mut statement = conn.prepare("SELECT 1 FROM takeout.entry_info WHERE hash = ?1")?;
+            if statement.exists(params![hash])? {
+                return Ok(());
+            }
+            conn.execute(
+                "INSERT INTO takeout.entry_info SELECT * FROM main.entry_info WHERE hash = ?1",
+                params![hash],
+            )?;
+            conn.execute(
+                "INSERT OR IGNORE INTO takeout.media_bytes SELECT * FROM main.media_bytes WHERE hash = ?1",
+                params![hash],
+            )?;
+            conn.execute(
+                "INSERT OR IGNORE INTO takeout.entry_tags SELECT * FROM main.entry_tags WHERE hash = ?1",
+                params![hash],
+            )?;
+            conn.execute(
+                "INSERT OR IGNORE INTO takeout.entry_sources SELECT * FROM main.entry_sources WHERE hash = ?1",
+                params![hash],
+            )?;
+        }
+        EntryId::PoolEntry(link_id) => {
+            conn.execute(
+                "INSERT INTO takeout.entry_info SELECT * FROM main.entry_info WHERE link_id = ?1",
+                params![link_id],
+            )?;
+            conn.execute(
+                "INSERT OR IGNORE INTO takeout.entry_tags SELECT * FROM main.entry_tags WHERE link_id = ?1",
+                params![link_id],
+            )?;
+            conn.execute(
+                "INSERT OR IGNORE INTO takeout.entry_sources SELECT * FROM main.entry_sources WHERE link_id = ?1",
+                params![link_id],
+            )?;
+            conn.execute(
+                "INSERT INTO takeout.media_links SELECT * FROM main.media_links WHERE link_id = ?1",
+                params![link_id],
+            )?;
+            for hash in get_hashes_of_media_link_with_conn(conn, link_id)? {
+                export_entry_to_archive_with_conn(conn, &EntryId::MediaEntry(hash))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Exports entries (and all tag definitions) into a new encrypted archive at `archive_path`.
+/// `on_progress` is called before each entry with (index, total, entry_id); returning false cancels the takeout.
+pub fn export_entries_to_archive(
+    entry_ids: &Vec<EntryId>,
+    archive_path: &PathBuf,
+    archive_key: &String,
+    mut on_progress: impl FnMut(usize, usize, &EntryId) -> bool,
+) -> Result<usize> {
+    if archive_key.is_empty() {
+        return Err(anyhow!("archive key can't be empty"));
+    }
+    if archive_path.exists() {
+        fs::remove_file(archive_path)?;
+    }
+    let mut conn = initialize_database_connection()?;
+    attach_takeout_archive_with_conn(&conn, archive_path, archive_key)?;
+
+    let tx = conn.transaction()?;
+    tx.execute("INSERT INTO takeout.tag_info SELECT * FROM main.tag_info", [])?;
+    tx.execute("INSERT INTO takeout.tag_links SELECT * FROM main.tag_links", [])?;
+    tx.execute("INSERT INTO takeout.namespaces SELECT * FROM main.namespaces", [])?;
+
+    let mut was_cancelled = false;
+    for (index, entry_id) in entry_ids.iter().enumerate() {
+        if !on_progress(index, entry_ids.len(), entry_id) {
+            was_cancelled = true;
+            break;
+        }
+        export_entry_to_archive_with_conn(&tx, entry_id)?;
+    }
+
+    if was_cancelled {
+        tx.rollback()?;
+    } else {
+        tx.commit()?;
+    }
+    conn.execute("DETACH DATABASE takeout", [])?;
+    drop(conn);
+
+    if was_cancelled {
+        fs::remove_file(archive_path)?;
+        Err(anyhow!("takeout cancelled"))
+    } else {
+        Ok(entry_ids.len())
+    }
+}
+
+pub struct TakeoutImportSummary {
+    pub imported_count: usize,
+    pub duplicate_count: usize,
+}
+
+fn does_archive_pool_exist_with_conn(conn: &Connection, archive_link_id: &i32) -> Result<bool> {
+    let mut statement = conn.prepare("SELECT hash FROM takeout.media_links WHERE link_id = ?1 ORDER BY value ASC")?;
+    let archive_hashes = statement
+        .query_map(params![archive_link_id], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    if let Some(first_hash) = archive_hashes.first() {
+        for link_id in get_media_links_of_hash_with_conn(conn, first_hash)? {
+            if get_hashes_of_media_link_with_conn(conn, &link_id)? == archive_hashes {
+                return Ok(true);
+            }
+        }
     }
+    Ok(false)
+}
+
+/// Imports every entry of an encrypted takeout archive. Media already in the database are skipped,
+/// and pools are recreated under new link ids unless an identical pool already exists.
+pub fn import_entries_from_archive(
+    archive_path: &PathBuf,
+    archive_key: &String,
+    mut on_progress: impl FnMut(usize, usize, &EntryId) -> bool,
+) -> Result<TakeoutImportSummary> {
+    let mut conn = initialize_database_connection()?;
+    attach_takeout_archive_with_conn(&conn, archive_path, archive_key)?;
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO main.tag_info SELECT * FROM takeout.tag_info t
+            WHERE NOT EXISTS (SELECT 1 FROM main.tag_info m WHERE m.name = t.name AND m.namespace = t.namespace)",
+        [],
+    )?;
+    tx.execute(
+        "INSERT INTO main.tag_links SELECT * FROM takeout.tag_links t
+            WHERE NOT EXISTS (SELECT 1 FROM main.tag_links m WHERE m.type = t.type AND m.from_tag = t.from_tag AND m.to_tag = t.to_tag)",
+        [],
+    )?;
+    tx.execute(
+        "INSERT INTO main.namespaces SELECT * FROM takeout.namespaces t
+            WHERE NOT EXISTS (SELECT 1 FROM main.namespaces m WHERE m.namespace = t.namespace)",
+        [],
+    )?;
+
+    let mut archive_entry_ids = {
+        let mut statement = tx.prepare("SELECT hash, link_id FROM takeout.entry_info")?;
+        let entry_ids = statement
+            .query_map([], |row| entry_info_row_to_id(row))?
+            .filter_map(|id_res| id_res.ok())
+            .collect::<Vec<_>>();
+        entry_ids
+    };
+    // media first, so pools can link to them
+    archive_entry_ids.sort_by_key(|entry_id| entry_id.is_pool_entry_id());
+
+    let mut summary = TakeoutImportSummary {
+        imported_count: 0,
+        duplicate_count: 0,
+    };
+    let mut was_cancelled = false;
+    let total = archive_entry_ids.len();
+    for (index, entry_id) in archive_entry_ids.iter().enumerate() {
+        if !on_progress(index, total, entry_id) {
+            was_cancelled = true;
+            break;
+        }
+        match entry_id {
+            EntryId::MediaEntry(hash) => {
+                let mut statement = tx.prepare("SELECT 1 FROM main.entry_info WHERE hash = ?1")?;
+                if statement.exists(params![hash])? {
+                    summary.duplicate_count += 1;
+                    continue;
+                }
+                tx.execute(
+                    "INSERT INTO main.entry_info SELECT * FROM takeout.entry_info WHERE hash = ?1",
+                    params![hash],
+                )?;
+                tx.execute(
+                    "INSERT OR IGNORE INTO main.media_bytes SELECT * FROM takeout.media_bytes WHERE hash = ?1",
+                    params![hash],
+                )?;
+                tx.execute(
+                    "INSERT OR IGNORE INTO main.entry_tags SELECT * FROM takeout.entry_tags WHERE hash = ?1",
+                    params![hash],
+                )?;
+                tx.execute(
+                    "INSERT OR IGNORE INTO main.entry_sources SELECT * FROM takeout.entry_sources WHERE hash = ?1",
+                    params![hash],
+                )?;
+                // the archive's integer rows aren't copied, they're made again from the stored hash
+                let p_hash: Option<String> = tx.query_row("SELECT perceptual_hash FROM main.entry_info WHERE hash = ?1", params![hash], |row| {
+                    row.get(0)
+                })?;
+                set_perceptual_hash_bits_with_conn(&tx, hash, p_hash.as_deref())?;
+            }
+            EntryId::PoolEntry(archive_link_id) => {
+                if does_archive_pool_exist_with_conn(&tx, archive_link_id)? {
+                    summary.duplicate_count += 1;
+                    continue;
+                }
+                let link_id = create_new_link_with_conn(&tx)?;
+                tx.execute(
+                    "UPDATE main.entry_info SET
+                        score = (SELECT score FROM takeout.entry_info WHERE link_id = ?2),
+                        is_bookmarked = (SELECT is_bookmarked FROM takeout.entry_info WHERE link_id = ?2)
+                        WHERE link_id = ?1",
+                    params![link_id, archive_link_id],
+                )?;
+                tx.execute(
+                    "INSERT OR IGNORE INTO main.entry_tags (link_id, tag) SELECT ?1, tag FROM takeout.entry_tags WHERE link_id = ?2",
+                    params![link_id, archive_link_id],
+                )?;
+                tx.execute(
+                    "INSERT OR IGNORE INTO main.entry_sources (link_id, source) SELECT ?1, source FROM takeout.entry_sources WHERE link_id = ?2",
+                    params![link_id, archive_link_id],
+                )?;
+                tx.execute(
+                    "INSERT INTO main.media_links (link_id, value, type, hash) SELECT ?1, value, type, hash FROM takeout.media_links WHERE link_id = ?2",
+                    params![link_id, archive_link_id],
+                )?;
+                tx.execute(
+                    "UPDATE main.entry_info SET is_independant = 0 WHERE hash IN (SELECT hash FROM takeout.media_links WHERE link_id = ?1)",
+                    params![archive_link_id],
+                )?;
+            }
+        }
+        summary.imported_count += 1;
+    }
+
+    if was_cancelled {
+        tx.rollback()?;
+    } else {
+        tx.commit()?;
+        invalidate_similarity_index();
+    }
+    conn.execute("DETACH DATABASE takeout", [])?;
+
+    if was_cancelled {
+        Err(anyhow!("takeout import cancelled"))
+    } else {
+        Ok(summary)
+    }
+}
+
+pub fn generate_perceptual_hash(image: &DynamicImage) -> String {
+    let hasher = HasherConfig::new().hash_alg(HashAlg::DoubleGradient).to_hasher();
+    hex::encode(hasher.hash_image(image).as_bytes())
+}
+
+// marks a perceptual hash made of video frames, so it's never compared against an image's
+const VIDEO_HASH_PREFIX: &str = "v:";
+const VIDEO_HASH_SEPARATOR: char = ',';
+// a frame is hashed every this many milliseconds of video. sampling by time rather than by decoded frame means a copy at
+// another frame rate samples the same moments, and a trimmed or offset copy samples moments close to them
+const VIDEO_HASH_FRAME_INTERVAL_MS: i64 = 1000;
+// most frames hashed from a video, so only about the first minute of a long one is sampled
+const VIDEO_HASH_MAX_FRAMES: i64 = 64;
+
+pub fn is_video_perceptual_hash(p_hash: &str) -> bool {
+    p_hash.starts_with(VIDEO_HASH_PREFIX)
+}
+
+// reads a stored perceptual hash into integers, none if it isn't one this version made
+fn parse_perceptual_hash(p_hash: &str) -> Option<PerceptualHash> {
+    match p_hash.strip_prefix(VIDEO_HASH_PREFIX) {
+        Some(frames) => Some(PerceptualHash::Video(
+            frames
+                .split(VIDEO_HASH_SEPARATOR)
+                .map(similarity::hash_bits_from_hex)
+                .collect::<Option<Vec<_>>>()?,
+        )),
+        None => similarity::hash_bits_from_hex(p_hash).map(PerceptualHash::Image),
+    }
+}
+
+// keeps the integer rows of a media's perceptual hash in step with entry_info, none removes them. the caller
+// invalidates the similarity index once the write is committed
+fn set_perceptual_hash_bits_with_conn(conn: &Connection, hash: &String, p_hash: Option<&str>) -> Result<()> {
+    conn.execute("DELETE FROM perceptual_hash_bits WHERE hash = ?1", params![hash])?;
+    let (is_video, frames) = match p_hash.and_then(parse_perceptual_hash) {
+        Some(PerceptualHash::Image(bits)) => (false, vec![bits]),
+        Some(PerceptualHash::Video(frames)) => (true, frames),
+        None => return Ok(()),
+    };
+    let mut statement = conn.prepare("INSERT INTO perceptual_hash_bits (hash, frame, is_video, bits) VALUES (?1, ?2, ?3, ?4)")?;
+    for (frame, bits) in frames.into_iter().enumerate() {
+        // sqlite integers are signed, the bits are stored as they are
+        statement.execute(params![hash, frame, is_video, bits as i64])?;
+    }
+    Ok(())
+}
+
+/// Indexes the perceptual hashes of media stored before perceptual_hash_bits existed, returns how many were indexed
+pub fn sync_perceptual_hash_bits() -> Result<usize> {
+    let mut conn = initialize_database_connection()?;
+    let tx = conn.transaction()?;
+    let unindexed_media = {
+        let mut statement = tx.prepare(
+            "SELECT hash, perceptual_hash FROM entry_info WHERE hash IS NOT NULL AND perceptual_hash IS NOT NULL
+                AND hash NOT IN (SELECT hash FROM perceptual_hash_bits)",
+        )?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        let unindexed_media = rows.collect::<Result<Vec<_>, _>>()?;
+        unindexed_media
+    };
+    for (hash, p_hash) in &unindexed_media {
+        set_perceptual_hash_bits_with_conn(&tx, hash, Some(p_hash))?;
+    }
+    tx.commit()?;
+    if !unindexed_media.is_empty() {
+        invalidate_similarity_index();
+    }
+    Ok(unindexed_media.len())
+}
+
+// bumped once a write to perceptual_hash_bits is committed, the cached index is rebuilt once it falls behind
+static SIMILARITY_INDEX_GENERATION: AtomicUsize = AtomicUsize::new(0);
+static SIMILARITY_INDEX: Mutex<Option<(usize, Arc<SimilarityIndex>)>> = parking_lot::const_mutex(None);
+
+fn invalidate_similarity_index() {
+    SIMILARITY_INDEX_GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+fn load_similarity_index_with_conn(conn: &Connection) -> Result<Arc<SimilarityIndex>> {
+    let version = SIMILARITY_INDEX_GENERATION.load(Ordering::SeqCst);
+    let mut cached_index = SIMILARITY_INDEX.lock();
+    if let Some((cached_version, index)) = cached_index.as_ref() {
+        if *cached_version == version {
+            return Ok(Arc::clone(index));
+        }
+    }
+
+    let mut statement = conn.prepare("SELECT hash, is_video, bits FROM perceptual_hash_bits ORDER BY hash, frame")?;
+    let rows = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, bool>(1)?,
+            row.get::<_, i64>(2)? as similarity::HashBits,
+        ))
+    })?;
+    let mut index = SimilarityIndex::default();
+    let mut current: Option<(String, PerceptualHash)> = None;
+    for row in rows {
+        let (hash, is_video, bits) = row?;
+        match current.as_mut() {
+            Some((current_hash, PerceptualHash::Video(frames))) if *current_hash == hash => frames.push(bits),
+            _ => {
+                if let Some((finished_hash, p_hash)) = current.take() {
+                    index.insert(finished_hash, p_hash);
+                }
+                let p_hash = if is_video {
+                    PerceptualHash::Video(vec![bits])
+                } else {
+                    PerceptualHash::Image(bits)
+                };
+                current = Some((hash, p_hash));
+            }
+        }
+    }
+    if let Some((finished_hash, p_hash)) = current {
+        index.insert(finished_hash, p_hash);
+    }
+    let index = Arc::new(index);
+    *cached_index = Some((version, Arc::clone(&index)));
+    Ok(index)
+}
+
+/// The media within `max_distance` bits of a media's perceptual hash, closest first
+pub fn find_similar_entries(entry_id: &EntryId, max_distance: u32) -> Result<Vec<(EntryId, u32)>> {
+    let hash = entry_id.as_media_entry_id().context("only media entries have perceptual hashes")?;
     let conn = initialize_database_connection()?;
-    let all_entry_info = get_all_entry_info_with_conn(&conn)?;
-    let all_p_hashes = all_entry_info.into_iter().filter_map(|e| {
-        e.as_media_entry()
-            .and_then(|m| m.p_hash.clone().map(|p_hash| (e.entry_id().as_media_entry_id().unwrap().clone(), p_hash)))
-    }).collect::<Vec<_>>();
-    let mut duplicates: Vec<Vec<(String, String)>> = Vec::new();
-    for (media_hash, p_hash) in all_p_hashes {
-        let mut found_duplicate = false;
-        for duplicate_group in &mut duplicates {
-            if duplicate_group.iter().any(|(_, other_p_hash)| hamming_distance(&p_hash, other_p_hash) <= hamming_threshold) {
-                duplicate_group.push((media_hash.clone(), p_hash.clone()));
-                found_duplicate = true;
-                break;
+    let index = load_similarity_index_with_conn(&conn)?;
+    Ok(index
+        .neighbours(hash, max_distance)
+        .into_iter()
+        .map(|(other_hash, distance)| (EntryId::MediaEntry(other_hash.clone()), distance))
+        .collect())
+}
+
+/// Hashes a frame every `VIDEO_HASH_FRAME_INTERVAL_MS` from the start of the video, up to `VIDEO_HASH_MAX_FRAMES`.
+pub fn generate_video_perceptual_hash(bytes: &[u8]) -> Result<String> {
+    let ctx = egui::Context::default();
+    let player = Player::new_from_bytes(&ctx, bytes)?;
+    let mut frame_hashes = vec![];
+    let mut streamer = player.video_streamer.lock();
+    // a clip shorter than the interval, or without a known duration, still gets its first frame hashed
+    let frame_count = (player.duration_ms / VIDEO_HASH_FRAME_INTERVAL_MS).clamp(1, VIDEO_HASH_MAX_FRAMES);
+    for frame_index in 0..frame_count {
+        // seeking goes straight to the sampled time, so only the frames from the keyframe before it are decoded
+        if frame_index > 0 {
+            streamer.seek((frame_index * VIDEO_HASH_FRAME_INTERVAL_MS) as f32 / player.duration_ms as f32);
+        }
+        let frame = match streamer.recieve_next_packet_until_frame() {
+            Ok(frame) => frame,
+            Err(_) => break,
+        };
+        let image = video_frame_to_image(&player, &frame.pixels)?;
+        frame_hashes.push(generate_perceptual_hash(&DynamicImage::ImageRgba8(image)));
+    }
+    if frame_hashes.is_empty() {
+        return Err(anyhow!("couldn't decode any frames"));
+    }
+    Ok(format!("{VIDEO_HASH_PREFIX}{}", frame_hashes.join(&VIDEO_HASH_SEPARATOR.to_string())))
+}
+
+fn generate_media_perceptual_hash(bytes: &[u8], decoder: Decoder) -> Option<String> {
+    match decoder {
+        Decoder::Image => image::load_from_memory(bytes).ok().map(|image| generate_perceptual_hash(&image)),
+        Decoder::Video => generate_video_perceptual_hash(bytes).ok(),
+        Decoder::Undecoded => None,
+    }
+}
+
+pub struct SimilarMedia {
+    pub hash: String,
+    pub distance: u32,
+    pub dimensions: Option<(u32, u32)>,
+    pub thumbnail: Option<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+}
+
+/// For each perceptual hash, the closest media in the library within `max_distance` bits of it, if any.
+pub fn find_similar_media(p_hashes: &Vec<Option<String>>, max_distance: u32) -> Result<Vec<Option<SimilarMedia>>> {
+    let conn = initialize_database_connection()?;
+    let index = load_similarity_index_with_conn(&conn)?;
+
+    let mut similar_media = vec![];
+    for p_hash in p_hashes {
+        let closest = p_hash
+            .as_deref()
+            .and_then(parse_perceptual_hash)
+            .and_then(|p_hash| index.find_within(&p_hash, max_distance).into_iter().next());
+        similar_media.push(match closest {
+            Some((hash, distance)) => Some(SimilarMedia {
+                hash: hash.clone(),
+                distance,
+                dimensions: get_media_dimensions_with_conn(&conn, hash),
+                thumbnail: load_thumbnail_with_conn(&conn, &EntryId::MediaEntry(hash.clone())).ok(),
+            }),
+            None => None,
+        });
+    }
+    Ok(similar_media)
+}
+
+// only the header is read, videos have none the image crate knows
+fn get_media_dimensions_with_conn(conn: &Connection, hash: &String) -> Option<(u32, u32)> {
+    let bytes = get_media_bytes_with_conn(conn, hash).ok()?;
+    image::io::Reader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// One media of a duplicate group, with what's needed to choose which to keep
+pub struct DuplicateCandidate {
+    pub media_info: MediaInfo,
+    pub dimensions: Option<(u32, u32)>,
+    pub thumbnail: Option<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+}
+
+pub fn get_duplicate_candidates(hashes: &Vec<String>) -> Result<Vec<DuplicateCandidate>> {
+    let conn = initialize_database_connection()?;
+    let mut candidates = vec![];
+    for hash in hashes {
+        let entry_id = EntryId::MediaEntry(hash.clone());
+        let media_info = match get_entry_info_with_conn(&conn, &entry_id)? {
+            EntryInfo::MediaEntry(media_info) => media_info,
+            EntryInfo::PoolEntry(_) => return Err(anyhow!("{entry_id} isn't a media entry")),
+        };
+        candidates.push(DuplicateCandidate {
+            media_info,
+            dimensions: get_media_dimensions_with_conn(&conn, hash),
+            thumbnail: load_thumbnail_with_conn(&conn, &entry_id).ok(),
+        });
+    }
+    Ok(candidates)
+}
+
+/// Keeps `kept_hash` and deletes the other media of its duplicate group. When merging, the kept media also takes
+/// over their tags, sources, score, bookmark and pools
+pub fn resolve_duplicate_group(kept_hash: &String, deleted_hashes: &Vec<String>, merge: bool) -> Result<()> {
+    let mut conn = initialize_database_connection()?;
+    let tx = conn.transaction()?;
+    for deleted_hash in deleted_hashes {
+        if merge {
+            replace_media_with_conn(&tx, deleted_hash, kept_hash)?;
+        } else {
+            record_deleted_hash_with_conn(&tx, deleted_hash, Some("duplicate"))?;
+            delete_entry_with_conn(&tx, &EntryId::MediaEntry(deleted_hash.clone()))?;
+        }
+    }
+    tx.commit()?;
+    invalidate_similarity_index();
+    Ok(())
+}
+
+/// Two media marked as not duplicates of each other
+#[derive(Clone)]
+pub struct DismissedDuplicate {
+    pub hash: String,
+    pub other_hash: String,
+    pub date_dismissed: i64,
+}
+
+// pairs are stored in order so either way around finds them
+fn ordered_pair<'a>(hash: &'a String, other_hash: &'a String) -> (&'a String, &'a String) {
+    if hash <= other_hash {
+        (hash, other_hash)
+    } else {
+        (other_hash, hash)
+    }
+}
+
+/// Marks every pair in a duplicate group as not duplicates, so `find_duplicates` stops grouping them
+pub fn dismiss_duplicate_group(hashes: &Vec<String>) -> Result<()> {
+    let mut conn = initialize_database_connection()?;
+    let tx = conn.transaction()?;
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    for (index, hash) in hashes.iter().enumerate() {
+        for other_hash in &hashes[index + 1..] {
+            let (hash, other_hash) = ordered_pair(hash, other_hash);
+            tx.execute(
+                "INSERT OR REPLACE INTO dismissed_duplicates (hash, other_hash, date_dismissed) VALUES (?1, ?2, ?3)",
+                params![hash, other_hash, timestamp],
+            )?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn get_dismissed_duplicates() -> Result<Vec<DismissedDuplicate>> {
+    let conn = initialize_database_connection()?;
+    get_dismissed_duplicates_with_conn(&conn)
+}
+
+fn get_dismissed_duplicates_with_conn(conn: &Connection) -> Result<Vec<DismissedDuplicate>> {
+    let mut statement =
+        conn.prepare("SELECT hash, other_hash, date_dismissed FROM dismissed_duplicates ORDER BY date_dismissed DESC, hash, other_hash")?;
+    let dismissed_duplicates = statement
+        .query_map([], |row| {
+            Ok(DismissedDuplicate {
+                hash: row.get(0)?,
+                other_hash: row.get(1)?,
+                date_dismissed: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(dismissed_duplicates)
+}
+
+// the pair can be grouped as duplicates again afterwards
+pub fn undo_dismissed_duplicate(hash: &String, other_hash: &String) -> Result<()> {
+    let conn = initialize_database_connection()?;
+    let (hash, other_hash) = ordered_pair(hash, other_hash);
+    conn.execute(
+        "DELETE FROM dismissed_duplicates WHERE hash = ?1 AND other_hash = ?2",
+        params![hash, other_hash],
+    )?;
+    Ok(())
+}
+
+pub fn clear_dismissed_duplicates() -> Result<()> {
+    let conn = initialize_database_connection()?;
+    conn.execute("DELETE FROM dismissed_duplicates", [])?;
+    Ok(())
+}
+
+/// Groups of media whose perceptual hashes are within the near duplicate distance of the first in the group, with
+/// their stored perceptual hashes. Pairs marked as not duplicates are never in the same group
+pub fn find_duplicates() -> Result<Vec<Vec<(EntryId, String)>>> {
+    let max_distance = Config::global().import.near_duplicate_distance;
+    let conn = initialize_database_connection()?;
+    let index = load_similarity_index_with_conn(&conn)?;
+    let all_p_hashes = {
+        let mut statement =
+            conn.prepare("SELECT hash, perceptual_hash FROM entry_info WHERE hash IS NOT NULL AND perceptual_hash IS NOT NULL ORDER BY rowid")?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        let all_p_hashes = rows.collect::<Result<Vec<_>, _>>()?;
+        all_p_hashes
+    };
+    let p_hashes_by_hash = all_p_hashes.iter().cloned().collect::<HashMap<_, _>>();
+    let mut dismissed_partners: HashMap<String, HashSet<String>> = HashMap::new();
+    for dismissed_duplicate in get_dismissed_duplicates_with_conn(&conn)? {
+        dismissed_partners
+            .entry(dismissed_duplicate.hash.clone())
+            .or_default()
+            .insert(dismissed_duplicate.other_hash.clone());
+        dismissed_partners
+            .entry(dismissed_duplicate.other_hash)
+            .or_default()
+            .insert(dismissed_duplicate.hash);
+    }
+
+    // a group is its first media and the ungrouped media within the distance of it, so similarity doesn't chain
+    // from one media to the next into groups of unrelated media
+    let mut grouped_hashes = HashSet::new();
+    let mut duplicates = vec![];
+    for (seed_hash, _p_hash) in &all_p_hashes {
+        if !grouped_hashes.insert(seed_hash.clone()) {
+            continue;
+        }
+        let mut duplicate_group = vec![seed_hash.clone()];
+        for (neighbour_hash, _distance) in index.neighbours(seed_hash, max_distance) {
+            if grouped_hashes.contains(neighbour_hash) {
+                continue;
+            }
+            // a media marked as not a duplicate of any media already in the group stays out of it
+            if let Some(partners) = dismissed_partners.get(neighbour_hash) {
+                if duplicate_group.iter().any(|hash| partners.contains(hash)) {
+                    continue;
+                }
             }
+            grouped_hashes.insert(neighbour_hash.clone());
+            duplicate_group.push(neighbour_hash.clone());
         }
-        if !found_duplicate {
-            duplicates.push(vec![(media_hash.clone(), p_hash.clone())]);
+        if duplicate_group.len() > 1 {
+            duplicates.push(duplicate_group);
         }
     }
-    Ok(duplicates.into_iter().map(|v| v.into_iter().map(|(m, p)| (EntryId::MediaEntry(m), p)).collect::<Vec<_>>()).filter(|v| v.len() > 1).collect::<Vec<_>>())
+    Ok(duplicates
+        .into_iter()
+        .map(|duplicate_group| {
+            duplicate_group
+                .into_iter()
+                .filter_map(|hash| p_hashes_by_hash.get(&hash).cloned().map(|p_hash| (EntryId::MediaEntry(hash), p_hash)))
+                .collect()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import_report_csv_quotes_fields_that_need_it() {
+        let rows = vec![
+            ImportReportRow {
+                path: String::from("/imports/plain.png"),
+                outcome: String::from("success"),
+                hash: Some(String::from("abc123")),
+                pool: Some(7),
+                error: None,
+            },
+            ImportReportRow {
+                path: String::from("/imports/a, \"quoted\"\nname.png"),
+                outcome: String::from("fail"),
+                hash: None,
+                pool: None,
+                error: Some(String::from("couldn't decode: bad header, \"IHDR\"\r\n")),
+            },
+        ];
+        assert_eq!(
+            import_report_csv(&rows),
+            "path,outcome,hash,pool,error\n\
+            /imports/plain.png,success,abc123,7,\n\
+            \"/imports/a, \"\"quoted\"\"\nname.png\",fail,,,\"couldn't decode: bad header, \"\"IHDR\"\"\r\n\"\n"
+        );
+    }
 }
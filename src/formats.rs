@@ -0,0 +1,161 @@
+use crate::archive;
+
+// what turns a format's bytes into frames for thumbnails, previews and perceptual hashes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decoder {
+    // the image crate
+    Image,
+    // ffmpeg, through egui_video
+    Video,
+    // stored and exported as is, without a thumbnail or perceptual hash
+    Undecoded,
+}
+
+pub struct MediaFormat {
+    // the first is the one stored, the rest are aliases that other tools report
+    pub mimes: &'static [&'static str],
+    pub extensions: &'static [&'static str],
+    // the extension group it's listed under by default
+    pub group: &'static str,
+    pub decoder: Decoder,
+}
+
+pub const IMAGE_GROUP: &str = "image";
+pub const MOVIE_GROUP: &str = "movie";
+pub const ARCHIVE_GROUP: &str = "archive";
+
+// avif decoding needs dav1d, which is only linked with the avif feature
+const AVIF_DECODER: Decoder = if cfg!(feature = "avif") { Decoder::Image } else { Decoder::Undecoded };
+
+// every format that can be imported. animated pngs are decoded as their first frame
+pub const MEDIA_FORMATS: &[MediaFormat] = &[
+    MediaFormat {
+        mimes: &["image/png", "image/apng"],
+        extensions: &["png", "apng"],
+        group: IMAGE_GROUP,
+        decoder: Decoder::Image,
+    },
+    MediaFormat {
+        mimes: &["image/jpeg", "image/pjpeg"],
+        extensions: &["jpg", "jpeg", "jpe", "jfif"],
+        group: IMAGE_GROUP,
+        decoder: Decoder::Image,
+    },
+    MediaFormat {
+        mimes: &["image/webp"],
+        extensions: &["webp"],
+        group: IMAGE_GROUP,
+        decoder: Decoder::Image,
+    },
+    MediaFormat {
+        mimes: &["image/bmp", "image/x-ms-bmp"],
+        extensions: &["bmp"],
+        group: IMAGE_GROUP,
+        decoder: Decoder::Image,
+    },
+    MediaFormat {
+        mimes: &["image/tiff"],
+        extensions: &["tif", "tiff"],
+        group: IMAGE_GROUP,
+        decoder: Decoder::Image,
+    },
+    MediaFormat {
+        mimes: &["image/vnd.microsoft.icon", "image/x-icon"],
+        extensions: &["ico"],
+        group: IMAGE_GROUP,
+        decoder: Decoder::Image,
+    },
+    MediaFormat {
+        mimes: &["image/avif"],
+        extensions: &["avif"],
+        group: IMAGE_GROUP,
+        decoder: AVIF_DECODER,
+    },
+    MediaFormat {
+        mimes: &["image/gif"],
+        extensions: &["gif"],
+        group: MOVIE_GROUP,
+        decoder: Decoder::Video,
+    },
+    MediaFormat {
+        mimes: &["video/mp4"],
+        extensions: &["mp4"],
+        group: MOVIE_GROUP,
+        decoder: Decoder::Video,
+    },
+    MediaFormat {
+        mimes: &["video/x-m4v"],
+        extensions: &["m4v"],
+        group: MOVIE_GROUP,
+        decoder: Decoder::Video,
+    },
+    MediaFormat {
+        mimes: &["video/webm"],
+        extensions: &["webm"],
+        group: MOVIE_GROUP,
+        decoder: Decoder::Video,
+    },
+    MediaFormat {
+        mimes: &["video/x-matroska"],
+        extensions: &["mkv"],
+        group: MOVIE_GROUP,
+        decoder: Decoder::Video,
+    },
+    MediaFormat {
+        mimes: &["video/quicktime"],
+        extensions: &["mov", "qt"],
+        group: MOVIE_GROUP,
+        decoder: Decoder::Video,
+    },
+];
+
+pub fn format_of_mime(mime: &str) -> Option<&'static MediaFormat> {
+    let essence = mime.split(';').next().unwrap_or_default().trim().to_lowercase();
+    MEDIA_FORMATS.iter().find(|format| format.mimes.contains(&essence.as_str()))
+}
+
+pub fn format_of_extension(extension: &str) -> Option<&'static MediaFormat> {
+    let extension = extension.trim_start_matches('.').to_lowercase();
+    MEDIA_FORMATS.iter().find(|format| format.extensions.contains(&extension.as_str()))
+}
+
+// maps aliases onto the mime a format is stored under, unknown mimes are left alone
+pub fn canonical_mime(mime: &str) -> String {
+    match format_of_mime(mime) {
+        Some(format) => format.mimes[0].to_string(),
+        None => mime.to_string(),
+    }
+}
+
+pub fn decoder_of_mime(mime: &str) -> Decoder {
+    format_of_mime(mime).map(|format| format.decoder).unwrap_or(Decoder::Undecoded)
+}
+
+pub fn is_video_mime(mime: &str) -> bool {
+    decoder_of_mime(mime) == Decoder::Video
+}
+
+pub fn is_supported_extension(extension: &str) -> bool {
+    format_of_extension(extension).is_some() || archive::ARCHIVE_EXTENSIONS.contains(&extension.trim_start_matches('.').to_lowercase().as_str())
+}
+
+pub fn media_extensions() -> Vec<&'static str> {
+    MEDIA_FORMATS.iter().flat_map(|format| format.extensions.iter().copied()).collect()
+}
+
+// the groups the importer's extension filter starts out with
+pub fn default_extension_groups() -> Vec<(&'static str, Vec<&'static str>)> {
+    let mut extension_groups = [IMAGE_GROUP, MOVIE_GROUP]
+        .into_iter()
+        .map(|group| {
+            let extensions = MEDIA_FORMATS
+                .iter()
+                .filter(|format| format.group == group)
+                .flat_map(|format| format.extensions.iter().copied())
+                .collect();
+            (group, extensions)
+        })
+        .collect::<Vec<_>>();
+    extension_groups.push((ARCHIVE_GROUP, archive::ARCHIVE_EXTENSIONS.to_vec()));
+    extension_groups
+}
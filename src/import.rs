@@ -1,26 +1,50 @@
 use super::ui;
+use crate::app::{SharedState, UpdateFlag};
+use crate::archive;
+use crate::config;
+use crate::config::{Config, ImportRule, OrganizeAction, OrganizeRule, PoolMapping};
 use crate::data;
+use crate::data::DuplicateAction;
+use crate::data::EntryId;
+use crate::data::ImportationMetadata;
+use crate::data::ImportSessionFile;
 use crate::data::ImportationStatus;
 use crate::data::RegistrationForm;
+use crate::formats;
+use crate::formats::Decoder;
+use crate::tags::Tag;
 use crate::ui::preview_ui::MediaPreview;
-use anyhow::{Error, Result};
+use crate::ui::ToastsRef;
+use crate::util;
+use anyhow::{anyhow, Context, Error, Result};
+use arboard::Clipboard;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use image::RgbaImage;
 
 
 use parking_lot::Mutex;
 use poll_promise::Promise;
-use std::collections::HashMap;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use std::{
+    fmt,
     fs::{self, DirEntry, File},
-    io::Read,
-    path::PathBuf,
-    sync::Arc,
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     thread::{self},
+    time::{Duration, SystemTime},
 };
 
 pub const SUCCESS_IMPORT_DIR: &str = "imported";
 pub const FAILED_IMPORT_DIR: &str = "failed";
 pub const DUPLICATE_IMPORT_DIR: &str = "duplicate";
+// lists patterns of files and folders to leave out of scans, one per line
+pub const IGNORE_FILE_NAME: &str = ".htoolignore";
 
 // #[derive(Clone)]
 pub struct ImportationEntry {
@@ -33,6 +57,18 @@ pub struct ImportationEntry {
     pub keep_bytes_loaded: bool,
 
     pub linking_dir: Option<String>,
+    pub linking_value: Option<i32>,
+    pub source_archive: Option<PathBuf>,
+    pub sidecar_paths: Vec<PathBuf>,
+    pub metadata: ImportationMetadata,
+    // set by hand in the importer, applied last so it wins over sidecars and rules
+    pub assigned_metadata: ImportationMetadata,
+    pub sidecar_error: Option<String>,
+    // from the folder names, when the pool mapping turns them into tags
+    pub folder_tags: Vec<Tag>,
+    pub near_duplicate: Option<NearDuplicate>,
+    // from the file's magic bytes, None if the type isn't recognised
+    pub sniffed_mime: Option<mime_guess::Mime>,
     pub bytes: Option<Promise<Result<Arc<Vec<u8>>>>>,
     pub thumbnail: Option<Promise<Result<MediaPreview>>>,
     pub is_archive: bool,
@@ -64,65 +100,749 @@ fn reverse_path_truncate(path: &PathBuf, num_components: usize) -> PathBuf {
     truncated_path
 }
 
+// a file that made it through the filters, waiting to be turned into an ImportationEntry
+struct ScannedFile {
+    dir_entry: DirEntry,
+    // the folders between the scanned folder and the file
+    folder_names: Vec<String>,
+    // the folder the file is in, with symlinks resolved
+    canonical_dir: PathBuf,
+}
+
+/// Live progress of a scan, read by the importer while the scan runs on another thread
+#[derive(Default)]
+pub struct ScanProgress {
+    pub found_count: AtomicUsize,
+}
+
+pub struct ScanResult {
+    pub import_entries: Vec<ImportationEntry>,
+    // files and folders that couldn't be read, with why
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+// the patterns of one .htoolignore, which apply to the folder it's in and everything under it
+#[derive(Clone)]
+struct IgnoreRules {
+    base_dir: PathBuf,
+    patterns: GlobSet,
+}
+
+impl IgnoreRules {
+    // like a .gitignore: blank lines and # comments are skipped, and a pattern without a slash matches at any
+    // depth. negated patterns aren't supported
+    fn read(directory_path: &Path) -> Result<Option<Self>> {
+        let ignore_file_path = directory_path.join(IGNORE_FILE_NAME);
+        if !ignore_file_path.is_file() {
+            return Ok(None);
+        }
+        let mut builder = GlobSetBuilder::new();
+        for line in fs::read_to_string(&ignore_file_path)?.lines() {
+            let pattern = line.trim().trim_end_matches('/');
+            if pattern.is_empty() || pattern.starts_with('#') || pattern.starts_with('!') {
+                continue;
+            }
+            let pattern = if pattern.contains('/') {
+                pattern.trim_start_matches('/').to_string()
+            } else {
+                format!("**/{pattern}")
+            };
+            builder.add(Glob::new(&pattern)?);
+        }
+        Ok(Some(Self {
+            base_dir: directory_path.to_path_buf(),
+            patterns: builder.build()?,
+        }))
+    }
+
+    fn is_ignored(&self, path: &Path) -> bool {
+        path.strip_prefix(&self.base_dir)
+            .map(|relative_path| self.patterns.is_match(relative_path))
+            .unwrap_or(false)
+    }
+}
+
+fn is_hidden(dir_entry: &DirEntry) -> bool {
+    if dir_entry.file_name().to_string_lossy().starts_with('.') {
+        return true;
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        if let Ok(metadata) = dir_entry.metadata() {
+            return metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0;
+        }
+    }
+    false
+}
+
+// a folder left for another walk, with the .htoolignore rules and canonical paths of the folders above it
+struct DeferredDir {
+    path: PathBuf,
+    folder_names: Vec<String>,
+    ignore_rules: Vec<IgnoreRules>,
+    ancestor_dirs: Vec<PathBuf>,
+}
+
+struct DirectoryWalk<'a> {
+    extension_filter: &'a Vec<String>,
+    import_config: &'a config::Import,
+    progress: &'a ScanProgress,
+    // canonical paths of the folders above the one being walked, so a symlink back up the tree isn't followed forever
+    ancestor_dirs: Vec<PathBuf>,
+    ignore_rules: Vec<IgnoreRules>,
+    scanned_files: Vec<ScannedFile>,
+    errors: Vec<(PathBuf, String)>,
+    // when set, folders are collected here instead of being walked
+    deferred_dirs: Option<Vec<DeferredDir>>,
+}
+
+impl DirectoryWalk<'_> {
+    fn walk(&mut self, directory_path: &Path, folder_names: Vec<String>) -> Result<()> {
+        let canonical_dir = fs::canonicalize(directory_path)?;
+        if self.ancestor_dirs.contains(&canonical_dir) {
+            self.errors
+                .push((directory_path.to_path_buf(), String::from("already scanned, a symlink leads back to it")));
+            return Ok(());
+        }
+        self.ancestor_dirs.push(canonical_dir);
+        let has_ignore_rules = match IgnoreRules::read(directory_path) {
+            Ok(Some(ignore_rules)) => {
+                self.ignore_rules.push(ignore_rules);
+                true
+            }
+            Ok(None) => false,
+            Err(e) => {
+                self.errors.push((directory_path.join(IGNORE_FILE_NAME), e.to_string()));
+                false
+            }
+        };
+        // the folder's rules and path are dropped even when it can't be read, the walk goes on with its siblings
+        let read_dir_result = fs::read_dir(directory_path).map(|dir_entries| {
+            for dir_entry_res in dir_entries {
+                match dir_entry_res {
+                    Ok(dir_entry) => self.visit(dir_entry, &folder_names),
+                    Err(e) => self.errors.push((directory_path.to_path_buf(), e.to_string())),
+                }
+            }
+        });
+        if has_ignore_rules {
+            self.ignore_rules.pop();
+        }
+        self.ancestor_dirs.pop();
+        Ok(read_dir_result?)
+    }
+
+    fn visit(&mut self, dir_entry: DirEntry, folder_names: &Vec<String>) {
+        let path = dir_entry.path();
+        if (!self.import_config.scan_hidden_files && is_hidden(&dir_entry)) || self.ignore_rules.iter().any(|rules| rules.is_ignored(&path)) {
+            return;
+        }
+        // follows symlinks, loops are caught by ancestor_dirs
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                self.errors.push((path, e.to_string()));
+                return;
+            }
+        };
+        if metadata.is_dir() {
+            if self.import_config.is_organize_dir(&path) {
+                return;
+            }
+            let mut folder_names = folder_names.clone();
+            folder_names.push(dir_entry.file_name().to_string_lossy().to_string());
+            if let Some(deferred_dirs) = self.deferred_dirs.as_mut() {
+                deferred_dirs.push(DeferredDir {
+                    path,
+                    folder_names,
+                    ignore_rules: self.ignore_rules.clone(),
+                    ancestor_dirs: self.ancestor_dirs.clone(),
+                });
+            } else if let Err(e) = self.walk(&path, folder_names) {
+                self.errors.push((path, e.to_string()));
+            }
+        } else {
+            let is_filtered_in = archive::filter_extension(&path)
+                .map(|ext| self.extension_filter.contains(&ext))
+                .unwrap_or(false);
+            if is_filtered_in {
+                self.scanned_files.push(ScannedFile {
+                    dir_entry,
+                    folder_names: folder_names.clone(),
+                    canonical_dir: self.ancestor_dirs.last().cloned().unwrap_or_default(),
+                });
+                self.progress.found_count.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    // walks the deferred folders on every core, each worker taking the next folder when it's done with one. the files
+    // and errors come back in the order the folders were found
+    fn walk_deferred_dirs(&mut self) {
+        let deferred_dirs = self.deferred_dirs.take().unwrap_or_default();
+        let (extension_filter, import_config, progress) = (self.extension_filter, self.import_config, self.progress);
+        let worker_count = thread::available_parallelism()
+            .map(|count| count.get())
+            .unwrap_or(1)
+            .min(deferred_dirs.len().max(1));
+        let jobs = Mutex::new(deferred_dirs.into_iter().enumerate().collect::<Vec<_>>());
+        let walks = Mutex::new(vec![]);
+        thread::scope(|scope| {
+            for _ in 0..worker_count {
+                scope.spawn(|| loop {
+                    let next_job = jobs.lock().pop();
+                    let (index, deferred_dir) = match next_job {
+                        Some(job) => job,
+                        None => break,
+                    };
+                    let mut directory_walk = DirectoryWalk {
+                        extension_filter,
+                        import_config,
+                        progress,
+                        ancestor_dirs: deferred_dir.ancestor_dirs,
+                        ignore_rules: deferred_dir.ignore_rules,
+                        scanned_files: vec![],
+                        errors: vec![],
+                        deferred_dirs: None,
+                    };
+                    if let Err(e) = directory_walk.walk(&deferred_dir.path, deferred_dir.folder_names) {
+                        directory_walk.errors.push((deferred_dir.path, e.to_string()));
+                    }
+                    walks.lock().push((index, directory_walk.scanned_files, directory_walk.errors));
+                });
+            }
+        });
+        let mut walks = walks.into_inner();
+        walks.sort_by_key(|(index, _, _)| *index);
+        for (_index, scanned_files, errors) in walks {
+            self.scanned_files.extend(scanned_files);
+            self.errors.extend(errors);
+        }
+    }
+
+    // a folder that symlinks lead to through more than one path is kept under its lexically smallest path, so the
+    // pool its files land in doesn't depend on which walk happened to get there first
+    fn keep_smallest_path_of_each_dir(&mut self) {
+        let mut kept_dirs: HashMap<PathBuf, PathBuf> = HashMap::new();
+        for scanned_file in &self.scanned_files {
+            let dir_path = scanned_file.dir_entry.path().parent().map(Path::to_path_buf).unwrap_or_default();
+            kept_dirs
+                .entry(scanned_file.canonical_dir.clone())
+                .and_modify(|kept_dir| {
+                    if dir_path < *kept_dir {
+                        *kept_dir = dir_path.clone();
+                    }
+                })
+                .or_insert(dir_path);
+        }
+        let mut dropped_dirs = BTreeMap::new();
+        self.scanned_files.retain(|scanned_file| {
+            let dir_path = scanned_file.dir_entry.path().parent().map(Path::to_path_buf).unwrap_or_default();
+            let kept_dir = &kept_dirs[&scanned_file.canonical_dir];
+            if dir_path == *kept_dir {
+                true
+            } else {
+                dropped_dirs.insert(dir_path, kept_dir.clone());
+                false
+            }
+        });
+        for (dropped_dir, kept_dir) in dropped_dirs {
+            self.errors
+                .push((dropped_dir, format!("already scanned as {}, a symlink leads to it", kept_dir.display())));
+        }
+    }
+}
+
+/// Scans `directory_path` for files with an extension in `extension_filter`, skipping hidden files (unless configured
+/// otherwise) and anything matched by a `.htoolignore`. Files and folders that can't be read end up in the result's
+/// errors instead of failing the scan, only an unreadable `directory_path` does that. The folders directly in
+/// `directory_path` are walked in parallel. Files in `skipped_paths` are left out before any of them is read
 pub fn scan_directory(
     directory_path: PathBuf,
-    directory_level: usize,
-    linking_dir: Option<String>,
     extension_filter: &Vec<String>,
-) -> Result<Vec<ImportationEntry>> {
+    progress: &ScanProgress,
+    skipped_paths: &HashSet<PathBuf>,
+) -> Result<ScanResult> {
     puffin::profile_scope!("import_scan_directory");
 
-    let dir_entries_iter = fs::read_dir(directory_path)?;
-    let mut scanned_dir_entries = vec![];
-    'dir_entries: for (_index, dir_entry_res) in dir_entries_iter.enumerate() {
-        if let Ok(dir_entry) = dir_entry_res {
-            // TODO: don't error whole function for one entry (? below)
-            if dir_entry.metadata()?.is_dir() {
-                if vec![SUCCESS_IMPORT_DIR, FAILED_IMPORT_DIR, DUPLICATE_IMPORT_DIR].contains(&dir_entry.file_name().to_str().unwrap_or_default()) {
-                    continue;
-                }
-                let linking_dir = if let Some(linking_dir) = &linking_dir {
-                    Some(linking_dir.clone())
-                } else {
-                    if directory_level == 0 {
-                        let dir_name = dir_entry.file_name().into_string();
-                        if let Ok(dir_name) = dir_name {
-                            Some(dir_name)
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                };
+    let import_config = Config::global().import.clone();
+    // the scanned folder's own files are found here, the folders in it are walked in parallel
+    let mut directory_walk = DirectoryWalk {
+        extension_filter,
+        import_config: &import_config,
+        progress,
+        ancestor_dirs: vec![],
+        ignore_rules: vec![],
+        scanned_files: vec![],
+        errors: vec![],
+        deferred_dirs: Some(vec![]),
+    };
+    directory_walk.walk(&directory_path, vec![])?;
+    directory_walk.walk_deferred_dirs();
+    directory_walk.keep_smallest_path_of_each_dir();
 
-                let media_entries = scan_directory(dir_entry.path(), directory_level + 1, linking_dir, extension_filter)?;
-                scanned_dir_entries.extend(media_entries);
+    let scanned_files = directory_walk
+        .scanned_files
+        .into_iter()
+        .filter(|scanned_file| !skipped_paths.contains(&scanned_file.dir_entry.path()))
+        .map(|scanned_file| {
+            let (linking_dir, folder_tags) = map_folders(&scanned_file.folder_names, &import_config);
+            (scanned_file.dir_entry, linking_dir, folder_tags, scanned_file.folder_names.len())
+        })
+        .collect::<Vec<_>>();
+
+    // reading sidecars and sniffing types is spread over every core
+    let mut errors = directory_walk.errors;
+    let mut import_entries = vec![];
+    let built_entries = util::map_with_workers(scanned_files, |(dir_entry, linking_dir, folder_tags, directory_level)| {
+        let path = dir_entry.path();
+        let import_entry = ImportationEntry::new(dir_entry, &linking_dir, directory_level).map(|mut import_entry| {
+            import_entry.folder_tags = folder_tags;
+            import_entry
+        });
+        (path, import_entry)
+    });
+    for built_entry in built_entries {
+        match built_entry {
+            Some((_path, Ok(import_entry))) => import_entries.push(import_entry),
+            Some((path, Err(e))) => errors.push((path, e.to_string())),
+            None => errors.push((directory_path.clone(), String::from("a scanning worker failed"))),
+        }
+    }
+
+    if let Err(e) = sequence_pools(&mut import_entries) {
+        errors.push((directory_path, format!("{e}, pools are in natural order")));
+    }
+    Ok(ScanResult { import_entries, errors })
+}
+
+// the pool and tags a file gets from the folders it's in, see PoolMapping
+fn map_folders(folder_names: &[String], import_config: &config::Import) -> (Option<String>, Vec<Tag>) {
+    match import_config.pool_mapping {
+        PoolMapping::Depth => {
+            let linking_dir = (import_config.pool_depth > 0 && folder_names.len() >= import_config.pool_depth)
+                .then(|| folder_names[..import_config.pool_depth].join("/"));
+            (linking_dir, vec![])
+        }
+        PoolMapping::Leaf => ((!folder_names.is_empty()).then(|| folder_names.join("/")), vec![]),
+        PoolMapping::Tags => {
+            let namespaces = import_config.folder_tag_namespaces.split_whitespace().collect::<Vec<_>>();
+            let folder_tags = folder_names
+                .iter()
+                .enumerate()
+                .map(|(depth, folder_name)| {
+                    // tagstrings are split on whitespace
+                    let name = folder_name.split_whitespace().collect::<Vec<_>>().join("_");
+                    Tag::new(name, namespaces.get(depth).map(|namespace| namespace.to_string()), None)
+                })
+                .collect();
+            (None, folder_tags)
+        }
+    }
+}
+
+/// An import rule with its regexes compiled, so a batch only compiles them once rather than once per file
+pub struct CompiledImportRule {
+    pub rule: ImportRule,
+    path_regex: Option<Regex>,
+    file_name_regex: Option<Regex>,
+    archive_name_regex: Option<Regex>,
+}
+
+impl CompiledImportRule {
+    pub fn compile(rule: &ImportRule) -> Result<Self> {
+        // empty patterns match everything
+        let compile_regex = |pattern: &String| -> Result<Option<Regex>> {
+            if pattern.is_empty() {
+                Ok(None)
             } else {
-                let dir_entry_path = dir_entry.path();
-                if let Some(ext) = dir_entry_path.extension() {
-                    if !extension_filter.contains(&&ext.to_string_lossy().to_string()) {
-                        continue 'dir_entries;
-                    }
+                Ok(Some(Regex::new(pattern)?))
+            }
+        };
+        Ok(Self {
+            rule: rule.clone(),
+            path_regex: compile_regex(&rule.path_regex)?,
+            file_name_regex: compile_regex(&rule.file_name_regex)?,
+            archive_name_regex: compile_regex(&rule.archive_name_regex)?,
+        })
+    }
+}
+
+// rules that are disabled or have a bad regex are left out, so they never match
+pub fn compile_import_rules(rules: &Vec<ImportRule>) -> Vec<CompiledImportRule> {
+    rules
+        .iter()
+        .filter(|rule| rule.is_enabled)
+        .filter_map(|rule| CompiledImportRule::compile(rule).ok())
+        .collect()
+}
+
+pub fn sequence_regex() -> Result<Option<Regex>> {
+    compile_sequence_regex(&Config::global().import.sequence_regex)
+}
+
+fn compile_sequence_regex(sequence_regex: &str) -> Result<Option<Regex>> {
+    if sequence_regex.is_empty() {
+        return Ok(None);
+    }
+    Regex::new(sequence_regex)
+        .map(Some)
+        .with_context(|| format!("invalid pool sequence regex {sequence_regex}"))
+}
+
+fn sequence_number(sequence_regex: &Regex, file_label: &str) -> Option<u64> {
+    let captures = sequence_regex.captures(file_label)?;
+    captures.get(1).or(captures.get(0))?.as_str().parse().ok()
+}
+
+/// Orders the members of each pool and returns their 1-based position, in the same order as `members`.
+/// Members are given as (linking dir, file label). Entries without a pool get no position.
+/// Members the sequence regex picks a number out of go first, everything else falls back to natural sort.
+pub fn pool_positions(members: &[(Option<String>, String)], sequence_regex: Option<&Regex>) -> Vec<Option<i32>> {
+    let mut pools: HashMap<&String, Vec<usize>> = HashMap::new();
+    for (index, (linking_dir, _)) in members.iter().enumerate() {
+        if let Some(linking_dir) = linking_dir {
+            pools.entry(linking_dir).or_default().push(index);
+        }
+    }
+    let mut positions = vec![None; members.len()];
+    for (_linking_dir, mut indices) in pools {
+        let sort_key = |index: &usize| {
+            let file_label = &members[*index].1;
+            let sequence = sequence_regex.and_then(|sequence_regex| sequence_number(sequence_regex, file_label));
+            (sequence.is_none(), sequence.unwrap_or(0), file_label)
+        };
+        indices.sort_by(|a, b| {
+            let (a_unmatched, a_sequence, a_label) = sort_key(a);
+            let (b_unmatched, b_sequence, b_label) = sort_key(b);
+            a_unmatched
+                .cmp(&b_unmatched)
+                .then(a_sequence.cmp(&b_sequence))
+                .then_with(|| util::natural_cmp(a_label, b_label))
+        });
+        for (position, index) in indices.into_iter().enumerate() {
+            positions[index] = Some(position as i32 + 1);
+        }
+    }
+    positions
+}
+
+/// Sets the linking value of every pooled entry from its position in the pool.
+// an invalid sequence regex falls back to natural order, its error is still returned so it can be reported
+pub fn sequence_pools(import_entries: &mut Vec<ImportationEntry>) -> Result<()> {
+    let members = import_entries
+        .iter()
+        .map(|import_entry| (import_entry.linking_dir.clone(), import_entry.file_label.clone()))
+        .collect::<Vec<_>>();
+    let sequence_regex = sequence_regex();
+    let positions = pool_positions(&members, sequence_regex.as_ref().ok().and_then(|sequence_regex| sequence_regex.as_ref()));
+    for (import_entry, position) in import_entries.iter_mut().zip(positions) {
+        import_entry.linking_value = position;
+    }
+    sequence_regex.map(|_| ())
+}
+
+// rule pools are keyed apart from directory pools in the dir link map
+const RULE_POOL_PREFIX: &str = "rule::";
+
+pub fn rule_metadata(rule: &ImportRule) -> ImportationMetadata {
+    ImportationMetadata {
+        tags: Tag::from_tagstrings(&rule.add_tags),
+        score: rule.set_score,
+        is_bookmarked: rule.set_bookmark,
+        sources: vec![],
+    }
+}
+
+// sidecars are looked up both as "image.jpg.json" and "image.json"
+const SIDECAR_EXTENSIONS: [&str; 2] = ["json", "txt"];
+const SIDECAR_SOURCE_KEYS: [&str; 6] = ["source", "sources", "url", "urls", "post_url", "source_url"];
+const SIDECAR_BOOKMARK_KEYS: [&str; 3] = ["bookmarked", "is_bookmarked", "favorite"];
+
+fn find_sidecars(path: &Path) -> Vec<PathBuf> {
+    let mut sidecar_paths = vec![];
+    for ext in SIDECAR_EXTENSIONS {
+        let mut full_name_path = path.as_os_str().to_owned();
+        full_name_path.push(format!(".{ext}"));
+        for candidate_path in [PathBuf::from(full_name_path), path.with_extension(ext)] {
+            if candidate_path != path && candidate_path.is_file() && !sidecar_paths.contains(&candidate_path) {
+                sidecar_paths.push(candidate_path);
+            }
+        }
+    }
+    sidecar_paths
+}
+
+fn read_sidecar_metadata(sidecar_paths: &Vec<PathBuf>) -> Result<ImportationMetadata> {
+    let mut metadata = ImportationMetadata::default();
+    for sidecar_path in sidecar_paths {
+        let contents = fs::read_to_string(sidecar_path)?;
+        let is_json = sidecar_path.extension().map(|ext| ext == "json").unwrap_or(false);
+        let sidecar_metadata = if is_json {
+            parse_json_sidecar(&contents, Config::global().general.entry_max_score as i64)
+        } else {
+            Ok(parse_txt_sidecar(&contents))
+        };
+        metadata.merge(sidecar_metadata.with_context(|| format!("{}", sidecar_path.display()))?);
+    }
+    Ok(metadata)
+}
+
+// tags can't contain whitespace, so "long hair" from other tools becomes "long_hair"
+fn sidecar_tag(tagstring: &str, namespace: Option<&str>) -> Tag {
+    let tagstring = tagstring.split_whitespace().collect::<Vec<_>>().join("_");
+    match namespace {
+        Some(namespace) => Tag::new(tagstring, Some(namespace.to_string()), None),
+        None => Tag::from_tagstring(&tagstring),
+    }
+}
+
+// one tag per line, urls are taken as sources
+fn parse_txt_sidecar(contents: &str) -> ImportationMetadata {
+    let mut metadata = ImportationMetadata::default();
+    for line in contents.lines().map(|line| line.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        } else if line.starts_with("http://") || line.starts_with("https://") {
+            metadata.sources.push(line.to_string());
+        } else {
+            metadata.tags.push(sidecar_tag(line, None));
+        }
+    }
+    metadata
+}
+
+// handles the common downloader layouts: "tags" as a list or a space separated string, namespaced "tags_<namespace>"
+// lists, source urls, and a score if it fits within `max_score`
+fn parse_json_sidecar(contents: &str, max_score: i64) -> Result<ImportationMetadata> {
+    let json: serde_json::Value = serde_json::from_str(contents)?;
+    let object = json.as_object().ok_or(anyhow!("sidecar isn't a json object"))?;
+    let json_strings = |value: &serde_json::Value, split_whitespace: bool| -> Vec<String> {
+        let values = match value {
+            serde_json::Value::Array(values) => values.iter().collect::<Vec<_>>(),
+            value => vec![value],
+        };
+        let mut strings = vec![];
+        for value in values {
+            let string = match value {
+                serde_json::Value::String(string) => Some(string.clone()),
+                serde_json::Value::Object(object) => object.get("name").and_then(|name| name.as_str()).map(|name| name.to_string()),
+                _ => None,
+            };
+            if let Some(string) = string {
+                if split_whitespace {
+                    strings.extend(string.split_whitespace().map(|s| s.to_string()));
+                } else {
+                    strings.push(string);
                 }
+            }
+        }
+        strings
+    };
 
-                scanned_dir_entries.push(ImportationEntry::new(dir_entry, &linking_dir, directory_level)?)
+    let mut metadata = ImportationMetadata::default();
+    for (key, value) in object {
+        let key = key.to_lowercase();
+        let split_whitespace = value.is_string();
+        if key == "tags" || key == "tag_string" {
+            metadata
+                .tags
+                .extend(json_strings(value, split_whitespace).iter().map(|tag| sidecar_tag(tag, None)));
+        } else if let Some(namespace) = key.strip_prefix("tags_").or(key.strip_prefix("tag_string_")) {
+            let namespace = if namespace == "general" { None } else { Some(namespace) };
+            metadata
+                .tags
+                .extend(json_strings(value, split_whitespace).iter().map(|tag| sidecar_tag(tag, namespace)));
+        } else if SIDECAR_SOURCE_KEYS.contains(&key.as_str()) {
+            metadata
+                .sources
+                .extend(json_strings(value, false).into_iter().filter(|source| source.starts_with("http")));
+        } else if SIDECAR_BOOKMARK_KEYS.contains(&key.as_str()) {
+            metadata.is_bookmarked = value.as_bool().or(metadata.is_bookmarked);
+        } else if key == "score" {
+            if let Some(score) = value.as_i64() {
+                if (0..=max_score).contains(&score) {
+                    metadata.score = Some(score);
+                }
             }
         }
     }
+    Ok(metadata)
+}
+
+// nested archives are extracted in place of themselves, up to this depth
+const MAX_NESTED_ARCHIVE_DEPTH: usize = 8;
+
+fn extract_archive_recursive(archive_path: &Path, output_dir: &Path, depth: usize, on_progress: &mut dyn FnMut(f32)) -> Result<()> {
+    archive::open_archive(archive_path)?.extract_all(output_dir, on_progress)?;
+    if depth >= MAX_NESTED_ARCHIVE_DEPTH {
+        return Ok(());
+    }
+    for (dir_entry, _relative_path) in walk_directory(output_dir, Path::new(""))? {
+        let member_path = dir_entry.path();
+        if archive::is_archive(&member_path) {
+            // the nested archive is moved aside so its contents can take its place as a folder of the same name
+            let file_name = member_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let moved_archive_path = member_path.with_file_name(format!(".nested.{file_name}"));
+            fs::rename(&member_path, &moved_archive_path)?;
+            extract_archive_recursive(&moved_archive_path, &member_path, depth + 1, &mut |_| ())
+                .map_err(|e| anyhow!("failed to extract nested archive {file_name}: {e}"))?;
+            fs::remove_file(&moved_archive_path)?;
+        }
+    }
+    Ok(())
+}
 
-    Ok(scanned_dir_entries)
+// every file under directory_path, along with its path relative to directory_path
+fn walk_directory(directory_path: &Path, relative_path: &Path) -> Result<Vec<(DirEntry, PathBuf)>> {
+    let mut files = vec![];
+    for dir_entry in fs::read_dir(directory_path)? {
+        let dir_entry = dir_entry?;
+        let relative_path = relative_path.join(dir_entry.file_name());
+        if dir_entry.metadata()?.is_dir() {
+            files.extend(walk_directory(&dir_entry.path(), &relative_path)?);
+        } else {
+            files.push((dir_entry, relative_path));
+        }
+    }
+    Ok(files)
+}
+
+/// Extracts an archive (and any archives nested inside it) into `output_dir` and scans the result.
+/// Each folder inside the archive becomes its own pool, with members ordered by their path in the archive (see `sequence_pools`).
+pub fn extract_archive(
+    archive_path: &Path,
+    output_dir: &Path,
+    extension_filter: &Vec<String>,
+    on_progress: &mut dyn FnMut(f32),
+) -> Result<Vec<ImportationEntry>> {
+    extract_archive_recursive(archive_path, output_dir, 0, on_progress)?;
+
+    let mut members = walk_directory(output_dir, Path::new(""))?;
+    members.retain(|(dir_entry, _)| {
+        let member_path = dir_entry.path();
+        member_path
+            .extension()
+            .map(|ext| extension_filter.contains(&ext.to_string_lossy().to_lowercase()) && !archive::is_archive(&member_path))
+            .unwrap_or(false)
+    });
+
+    let archive_label = archive_path.to_string_lossy().to_string();
+    let archive_file_name = archive_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let mut import_entries = vec![];
+    for (dir_entry, relative_path) in members {
+        let inner_dir = relative_path.parent().map(|p| p.to_string_lossy().replace("\\", "/")).unwrap_or_default();
+        let linking_dir = if inner_dir.is_empty() {
+            archive_label.clone()
+        } else {
+            format!("{archive_label}/{inner_dir}")
+        };
+        let mut import_entry = ImportationEntry::new(dir_entry, &Some(linking_dir), 0)?;
+        import_entry.file_label = format!("{archive_file_name}/{}", relative_path.to_string_lossy().replace("\\", "/"));
+        import_entry.source_archive = Some(archive_path.to_path_buf());
+        import_entries.push(import_entry);
+    }
+    // a bad sequence regex was already reported by the scan that found the archive
+    let _ = sequence_pools(&mut import_entries);
+    Ok(import_entries)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NearDuplicateAction {
+    Skip,
+    ImportAnyway,
+    Replace,
+}
+
+impl fmt::Display for NearDuplicateAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NearDuplicateAction::Skip => write!(f, "skip"),
+            NearDuplicateAction::ImportAnyway => write!(f, "import anyway"),
+            NearDuplicateAction::Replace => write!(f, "replace lower resolution copy"),
+        }
+    }
+}
+
+// library media that looks like an importation entry, found by comparing perceptual hashes
+pub struct NearDuplicate {
+    pub hash: String,
+    pub distance: u32,
+    pub dimensions: Option<(u32, u32)>,
+    pub library_dimensions: Option<(u32, u32)>,
+    pub library_thumbnail: Option<Promise<Result<MediaPreview>>>,
+    pub action: NearDuplicateAction,
+}
+
+impl NearDuplicate {
+    // replacing only makes sense when the incoming copy has more pixels than the one in the library
+    pub fn can_replace(&self) -> bool {
+        match (self.dimensions, self.library_dimensions) {
+            (Some((width, height)), Some((library_width, library_height))) => {
+                width as u64 * height as u64 > library_width as u64 * library_height as u64
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Compares the images to the library by perceptual hash. Each image is given as its path, along with its
+/// bytes if they are already loaded (extracted files no longer exist on disk).
+/// Returns the paths of the images that have a near duplicate in the library.
+pub fn find_near_duplicates(images: Vec<(PathBuf, Option<Arc<Vec<u8>>>)>, max_distance: u32) -> Result<Vec<(PathBuf, NearDuplicate)>> {
+    // decoding and hashing is spread over every core, the library is then searched through its similarity index
+    let (dimensions, p_hashes): (Vec<_>, Vec<_>) = util::map_with_workers(images.clone(), |(path, bytes)| {
+        let is_image = path
+            .extension()
+            .and_then(|ext| formats::format_of_extension(&ext.to_string_lossy()))
+            .map(|format| format.decoder == Decoder::Image)
+            .unwrap_or(false);
+        let image = if is_image {
+            match bytes {
+                Some(bytes) => image::load_from_memory(&bytes).ok(),
+                None => fs::read(&path).ok().and_then(|bytes| image::load_from_memory(&bytes).ok()),
+            }
+        } else {
+            None
+        };
+        (
+            image.as_ref().map(|image| (image.width(), image.height())),
+            image.as_ref().map(data::generate_perceptual_hash),
+        )
+    })
+    .into_iter()
+    .map(|hashed_image| hashed_image.unwrap_or((None, None)))
+    .unzip();
+
+    let similar_media = data::find_similar_media(&p_hashes, max_distance)?;
+    let mut near_duplicates = vec![];
+    for (((path, _bytes), dimensions), similar_media) in images.into_iter().zip(dimensions).zip(similar_media) {
+        if let Some(similar_media) = similar_media {
+            let library_thumbnail = similar_media
+                .thumbnail
+                .map(|thumbnail| Promise::from_ready(ui::generate_retained_image(&thumbnail).map(MediaPreview::Picture)));
+            near_duplicates.push((
+                path,
+                NearDuplicate {
+                    hash: similar_media.hash,
+                    distance: similar_media.distance,
+                    dimensions,
+                    library_dimensions: similar_media.dimensions,
+                    library_thumbnail,
+                    action: NearDuplicateAction::Skip,
+                },
+            ));
+        }
+    }
+    Ok(near_duplicates)
 }
 
 impl ImportationEntry {
     pub fn new(dir_entry: DirEntry, linking_dir: &Option<String>, directory_level: usize) -> Result<Self> {
-        let mut is_archive = false;
         let dir_entry_path = dir_entry.path();
-        if let Some(ext) = dir_entry_path.extension() {
-            if ext == "zip" {
-                is_archive = true;
-            }
-        }
+        let is_archive = archive::is_archive(&dir_entry_path);
 
         let file_label = reverse_path_truncate(&dir_entry_path, 2 + directory_level)
             .to_str()
@@ -131,6 +851,12 @@ impl ImportationEntry {
             .replace("\\", "/");
 
         let file_size = dir_entry.metadata()?.len();
+        let sidecar_paths = find_sidecars(&dir_entry_path);
+        let (metadata, sidecar_error) = match read_sidecar_metadata(&sidecar_paths) {
+            Ok(metadata) => (metadata, None),
+            Err(e) => (ImportationMetadata::default(), Some(e.to_string())),
+        };
+        let sniffed_mime = if is_archive { None } else { util::sniff_mime_of_file(&dir_entry_path) };
         Ok(ImportationEntry {
             thumbnail: None,
             keep_bytes_loaded: false,
@@ -143,9 +869,24 @@ impl ImportationEntry {
             is_archive,
             importation_status: None,
             linking_dir: linking_dir.clone(),
+            linking_value: None,
+            source_archive: None,
+            sidecar_paths,
+            metadata,
+            assigned_metadata: ImportationMetadata::default(),
+            sidecar_error,
+            folder_tags: vec![],
+            near_duplicate: None,
+            sniffed_mime,
         })
     }
-    pub fn generate_reg_form(&mut self, dir_link_map: Arc<Mutex<HashMap<String, i32>>>) -> Result<RegistrationForm> {
+    pub fn generate_reg_form(
+        &mut self,
+        dir_link_map: Arc<Mutex<HashMap<String, i32>>>,
+        duplicate_action: DuplicateAction,
+        import_session_id: Option<i64>,
+        import_rules: &[CompiledImportRule],
+    ) -> Result<RegistrationForm> {
         let bytes = self.bytes.as_ref();
         let fail = |_message: String| -> Result<_, Error> { Err(anyhow::Error::msg("bytes not loaded")) };
         match bytes {
@@ -156,8 +897,22 @@ impl ImportationEntry {
                 Some(Ok(bytes)) => {
                     let bytes = Arc::clone(bytes);
                     let dir_link_map = Arc::clone(&dir_link_map);
-                    let linking_value: Option<i32> = self.dir_entry.path().file_stem().and_then(|fs| fs.to_string_lossy().parse().ok());
-                    let linking_dir = self.linking_dir.clone();
+                    let linking_value: Option<i32> = self
+                        .linking_value
+                        .or_else(|| self.dir_entry.path().file_stem().and_then(|fs| fs.to_string_lossy().parse().ok()));
+                    let mut linking_dir = self.linking_dir.clone();
+                    let mut metadata = self.metadata.clone();
+                    metadata.merge(ImportationMetadata {
+                        tags: self.folder_tags.clone(),
+                        ..Default::default()
+                    });
+                    for rule in self.matching_rules(import_rules) {
+                        metadata.merge(rule_metadata(rule));
+                        if !rule.assign_pool.is_empty() {
+                            linking_dir = Some(format!("{RULE_POOL_PREFIX}{}", rule.assign_pool));
+                        }
+                    }
+                    metadata.merge(self.assigned_metadata.clone());
                     let (sender, promise) = Promise::new();
                     self.importation_status = Some(promise);
                     Ok(RegistrationForm {
@@ -167,12 +922,117 @@ impl ImportationEntry {
                         importation_result_sender: sender,
                         linking_dir,
                         dir_link_map,
+                        metadata,
+                        duplicate_action,
+                        replaces: self
+                            .near_duplicate
+                            .as_ref()
+                            .filter(|near_duplicate| near_duplicate.action == NearDuplicateAction::Replace)
+                            .map(|near_duplicate| near_duplicate.hash.clone()),
+                        import_session_id,
+                        source_path: Some(self.source_path()),
                     })
                 }
             },
         }
     }
 
+    // what the file's folders map to, shown before importing
+    pub fn folder_mapping_label(&self) -> Option<String> {
+        if let Some(linking_dir) = &self.linking_dir {
+            Some(format!("pool {linking_dir}"))
+        } else if !self.folder_tags.is_empty() {
+            let tagstrings = self.folder_tags.iter().map(|tag| tag.to_tagstring()).collect::<Vec<_>>();
+            Some(format!("tags {}", tagstrings.join(" ")))
+        } else {
+            None
+        }
+    }
+
+    // the path the file came from, with extracted files shown inside their archive
+    pub fn source_path(&self) -> PathBuf {
+        match &self.source_archive {
+            Some(source_archive) => source_archive.with_file_name(&self.file_label),
+            None => self.dir_entry.path(),
+        }
+    }
+
+    fn image_dimensions(&self) -> Option<(u32, u32)> {
+        match self.bytes.as_ref().and_then(|promise| promise.ready()) {
+            Some(Ok(bytes)) => image::io::Reader::new(Cursor::new(bytes.as_slice()))
+                .with_guessed_format()
+                .ok()?
+                .into_dimensions()
+                .ok(),
+            _ => image::image_dimensions(self.dir_entry.path()).ok(),
+        }
+    }
+
+    pub fn matches_rule(&self, compiled_rule: &CompiledImportRule) -> Result<bool> {
+        let rule = &compiled_rule.rule;
+        let regex_matches = |regex: &Option<Regex>, text: &str| regex.as_ref().map(|regex| regex.is_match(text)).unwrap_or(true);
+        let source_path = self.source_path().to_string_lossy().replace("\\", "/");
+        let file_name = self.dir_entry.file_name().to_string_lossy().to_string();
+        let archive_name = self
+            .source_archive
+            .as_ref()
+            .map(|source_archive| source_archive.file_name().unwrap_or_default().to_string_lossy().to_string());
+
+        if !regex_matches(&compiled_rule.path_regex, &source_path) || !regex_matches(&compiled_rule.file_name_regex, &file_name) {
+            return Ok(false);
+        }
+        if !rule.archive_name_regex.is_empty() {
+            match archive_name {
+                Some(archive_name) if regex_matches(&compiled_rule.archive_name_regex, &archive_name) => (),
+                _ => return Ok(false),
+            }
+        }
+        if !rule.mime_prefix.is_empty() {
+            let mime = self.mime().map(|mime| mime.essence_str().to_string());
+            if !mime.map(|mime| mime.starts_with(&rule.mime_prefix)).unwrap_or(false) {
+                return Ok(false);
+            }
+        }
+        if rule.has_dimension_conditions() {
+            let (width, height) = self.image_dimensions().ok_or(anyhow!("couldn't read image dimensions"))?;
+            let within = |value: u32, min: u32, max: u32| value >= min && (max == 0 || value <= max);
+            if !within(width, rule.min_width, rule.max_width) || !within(height, rule.min_height, rule.max_height) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // rules that fail to evaluate (unreadable dimensions) are treated as not matching
+    pub fn matching_rules<'a>(&self, import_rules: &'a [CompiledImportRule]) -> Vec<&'a ImportRule> {
+        import_rules
+            .iter()
+            .filter(|compiled_rule| self.matches_rule(compiled_rule).unwrap_or(false))
+            .map(|compiled_rule| &compiled_rule.rule)
+            .collect()
+    }
+
+    // the sniffed type, falling back to the extension for formats infer doesn't know
+    pub fn mime(&self) -> Option<mime_guess::Mime> {
+        self.sniffed_mime.clone().or_else(|| mime_guess::from_path(self.dir_entry.path()).first())
+    }
+
+    // describes how the extension disagrees with the file's contents, if it does
+    pub fn mime_mismatch(&self) -> Option<String> {
+        let sniffed_mime = self.sniffed_mime.as_ref()?;
+        let guessed_mime = formats::canonical_mime(mime_guess::from_path(self.dir_entry.path()).first()?.essence_str());
+        if guessed_mime == sniffed_mime.essence_str() {
+            None
+        } else {
+            Some(format!("named as {} but contains {}", guessed_mime, sniffed_mime.essence_str()))
+        }
+    }
+
+    // the file followed by its sidecars, which are organized together
+    pub fn organize_paths(&self) -> Vec<PathBuf> {
+        std::iter::once(self.dir_entry.path()).chain(self.sidecar_paths.iter().cloned()).collect()
+    }
+
     pub fn load_bytes(&mut self) {
         let path = self.dir_entry.path().clone();
         let promise = Promise::spawn_thread("load_import_entry_bytes", move || {
@@ -222,6 +1082,14 @@ impl ImportationEntry {
         }
     }
 
+    // any duplicate, whatever was done about it
+    pub fn is_duplicate(&self) -> bool {
+        matches!(
+            self.importation_status.as_ref().and_then(|promise| promise.ready()),
+            Some(ImportationStatus::Duplicate(_))
+        )
+    }
+
     pub fn is_importable(&self) -> bool {
         if let Some(importation_promise) = self.importation_status.as_ref() {
             if let Some(importation_status) = importation_promise.ready() {
@@ -249,8 +1117,15 @@ impl ImportationEntry {
         if self.match_importation_status(ImportationStatus::Success) {
             add("imported")
         }
-        if self.match_importation_status(ImportationStatus::Duplicate) {
-            add("duplicate")
+        if let Some(ImportationStatus::Duplicate(duplicate_action)) = self.importation_status.as_ref().and_then(|promise| promise.ready()) {
+            match duplicate_action {
+                DuplicateAction::Skip => add("duplicate"),
+                DuplicateAction::AddToPool => add("duplicate (added to pool)"),
+                DuplicateAction::MergeTags => add("duplicate (merged tags)"),
+            }
+        }
+        if self.match_importation_status(ImportationStatus::PreviouslyDeleted) {
+            add("previously deleted")
         }
         if self.match_importation_status(ImportationStatus::Fail(anyhow::Error::msg(""))) {
             let error_message = {
@@ -274,6 +1149,15 @@ impl ImportationEntry {
                 add("couldn't generate thumbnail")
             }
         }
+        if let Some(near_duplicate) = &self.near_duplicate {
+            add(format!("possible duplicate of {}", EntryId::MediaEntry(near_duplicate.hash.clone())).as_str())
+        }
+        if let Some(sidecar_error) = &self.sidecar_error {
+            add(format!("couldn't read sidecar: {sidecar_error}").as_str())
+        }
+        if let Some(mime_mismatch) = self.mime_mismatch() {
+            add(mime_mismatch.as_str())
+        }
 
         let label = statuses.join(", ");
 
@@ -285,6 +1169,7 @@ impl ImportationEntry {
     }
 
     pub fn load_thumbnail(&mut self) {
+        let is_movie = self.mime().map(|mime| formats::is_video_mime(mime.essence_str())).unwrap_or(false);
         if let Some(bytes_promise) = self.bytes.as_ref() {
             if let Some(bytes_res) = bytes_promise.ready() {
                 let (sender, promise) = Promise::new();
@@ -296,7 +1181,7 @@ impl ImportationEntry {
                         thread::spawn(move || {
                             let bytes = &bytes as &[u8];
                             let generate_image = || -> Result<MediaPreview> {
-                                let pixels = data::generate_media_thumbnail(bytes, false)?;
+                                let pixels = data::generate_media_thumbnail(bytes, is_movie)?;
                                 Ok(MediaPreview::Picture(ui::generate_retained_image(&pixels)?))
                             };
                             sender.send(generate_image());
@@ -309,3 +1194,555 @@ impl ImportationEntry {
         }
     }
 }
+
+/// Applies an organize rule to an imported file and its sidecars (`paths`, file first). Moved files keep their
+/// path relative to `scan_root`, and a file is only trashed once its copy in the database checks out against its hash
+pub fn organize_file(paths: &Vec<PathBuf>, rule: &OrganizeRule, scan_root: &Path) -> Result<()> {
+    let entry_path = paths.first().ok_or(anyhow!("nothing to organize"))?;
+    match rule.action {
+        OrganizeAction::Leave => (),
+        OrganizeAction::Move => {
+            let relative_dir = entry_path
+                .parent()
+                .and_then(|parent| parent.strip_prefix(scan_root).ok())
+                .unwrap_or(Path::new(""));
+            // joining an absolute move_to replaces scan_root
+            let organize_dir = scan_root.join(&rule.move_to).join(relative_dir);
+            fs::create_dir_all(&organize_dir)?;
+            for path in paths.iter().filter(|path| path.exists()) {
+                let destination = organize_dir.join(path.file_name().unwrap_or_default());
+                // renaming fails across drives, so fall back to copying
+                if fs::rename(path, &destination).is_err() {
+                    fs::copy(path, &destination)?;
+                    fs::remove_file(path)?;
+                }
+            }
+        }
+        OrganizeAction::Trash => {
+            data::verify_stored_media(&fs::read(entry_path)?).map_err(|e| anyhow!("refusing to trash the original: {e}"))?;
+            trash::delete_all(paths.iter().filter(|path| path.exists()))?;
+        }
+    }
+    Ok(())
+}
+
+pub struct ClipboardMedia {
+    pub label: String,
+    // where the import report says it came from, the copied file or "clipboard/clipboard image"
+    pub source_path: PathBuf,
+    pub bytes: Vec<u8>,
+    pub mimetype: mime_guess::MimeGuess,
+}
+
+#[derive(Default)]
+pub struct ClipboardImportSummary {
+    pub imported_count: usize,
+    pub duplicate_count: usize,
+    pub deleted_count: usize,
+    pub failures: Vec<String>,
+}
+
+/// Reads an image or a list of copied file paths off the clipboard. Raw bitmap data is encoded to png.
+pub fn read_clipboard_media() -> Result<Vec<ClipboardMedia>> {
+    let mut clipboard = Clipboard::new()?;
+    if let Ok(image_data) = clipboard.get_image() {
+        let image = RgbaImage::from_raw(image_data.width as u32, image_data.height as u32, image_data.bytes.into_owned())
+            .ok_or(anyhow!("clipboard image data doesn't match its size"))?;
+        let mut png_bytes = vec![];
+        image.write_to(&mut Cursor::new(&mut png_bytes), image::ImageOutputFormat::Png)?;
+        return Ok(vec![ClipboardMedia {
+            label: String::from("clipboard image"),
+            source_path: Path::new(CLIPBOARD_SESSION_DIR).join("clipboard image"),
+            bytes: png_bytes,
+            mimetype: mime_guess::from_ext("png"),
+        }]);
+    }
+    let text = clipboard.get_text().unwrap_or_default();
+    let paths = text.lines().filter_map(clipboard_line_path).collect::<Vec<_>>();
+    if paths.is_empty() {
+        return Err(anyhow!("the clipboard has no image or file paths"));
+    }
+    paths
+        .into_iter()
+        .map(|path| {
+            Ok(ClipboardMedia {
+                label: path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+                bytes: fs::read(&path).with_context(|| format!("{}", path.display()))?,
+                mimetype: mime_guess::from_path(&path),
+                source_path: path,
+            })
+        })
+        .collect()
+}
+
+// copied files show up either as plain paths or as file:// uris, depending on the platform
+fn clipboard_line_path(line: &str) -> Option<PathBuf> {
+    let line = line.trim();
+    let path = match line.strip_prefix("file://") {
+        Some(uri_path) if cfg!(windows) => PathBuf::from(percent_decode(uri_path.trim_start_matches('/'))),
+        Some(uri_path) => PathBuf::from(percent_decode(uri_path)),
+        None => PathBuf::from(line),
+    };
+    path.is_file().then_some(path)
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            // from_str_radix would take a sign, so both characters have to be hex digits
+            .filter(|hex| bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(&String::from_utf8_lossy(hex), 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+// what clipboard import sessions show as their scan directory
+const CLIPBOARD_SESSION_DIR: &str = "clipboard";
+
+/// Registers media read from the clipboard, tagging each one with `tags`.
+pub fn import_clipboard_media(clipboard_media: Vec<ClipboardMedia>, tags: Vec<Tag>) -> Result<ClipboardImportSummary> {
+    // clipboard imports are sessions too, so they can be searched and reverted like any other
+    let mut files = clipboard_media
+        .iter()
+        .map(|clipboard_media| ImportSessionFile {
+            path: clipboard_media.source_path.clone(),
+            is_selected: true,
+            status: None,
+        })
+        .collect::<Vec<_>>();
+    let session_id = data::create_import_session(&PathBuf::from(CLIPBOARD_SESSION_DIR), &files)?;
+    let dir_link_map = Arc::new(Mutex::new(HashMap::new()));
+    let mut reg_forms = vec![];
+    let mut pending = vec![];
+    for clipboard_media in clipboard_media {
+        let (sender, promise) = Promise::new();
+        reg_forms.push(RegistrationForm {
+            bytes: Arc::new(clipboard_media.bytes),
+            mimetype: clipboard_media.mimetype,
+            importation_result_sender: sender,
+            linking_dir: None,
+            linking_value: None,
+            dir_link_map: Arc::clone(&dir_link_map),
+            metadata: ImportationMetadata {
+                tags: tags.clone(),
+                ..Default::default()
+            },
+            duplicate_action: Config::global().import.duplicate_action,
+            replaces: None,
+            import_session_id: Some(session_id),
+            source_path: Some(clipboard_media.source_path),
+        });
+        pending.push((clipboard_media.label, promise));
+    }
+    if let Err(e) = data::register_media_with_forms(reg_forms) {
+        data::finish_import_session(session_id)?;
+        return Err(e);
+    }
+
+    let mut summary = ClipboardImportSummary::default();
+    for ((label, promise), file) in pending.into_iter().zip(files.iter_mut()) {
+        let status = promise.block_and_take();
+        match &status {
+            ImportationStatus::Success => summary.imported_count += 1,
+            ImportationStatus::Duplicate(_) => summary.duplicate_count += 1,
+            ImportationStatus::PreviouslyDeleted => summary.deleted_count += 1,
+            ImportationStatus::Fail(e) => summary.failures.push(format!("{label}: {e}")),
+            ImportationStatus::Pending => (),
+        }
+        file.status = Some(status);
+    }
+    data::update_import_session(session_id, &files, &dir_link_map.lock())?;
+    data::finish_import_session(session_id)?;
+    Ok(summary)
+}
+
+// the watcher registers files in batches so a large drop doesn't load every file into memory at once
+const WATCH_IMPORT_BATCH_SIZE: usize = 50;
+
+pub struct FolderWatcher {
+    toasts: ToastsRef,
+    database_unlocked: UpdateFlag,
+    gallery_regenerate_flag: UpdateFlag,
+    dir_link_map: Arc<Mutex<HashMap<String, i32>>>,
+    file_snapshots: HashMap<PathBuf, (u64, Option<SystemTime>)>,
+    attempted_files: HashSet<PathBuf>,
+    last_error: Option<String>,
+}
+
+impl FolderWatcher {
+    /// Starts polling the landing folder and any configured watch folders in the background.
+    /// Polling is skipped while watching is disabled in the config or the database is locked.
+    pub fn start(shared_state: &SharedState) {
+        let mut watcher = FolderWatcher {
+            toasts: Arc::clone(&shared_state.toasts),
+            database_unlocked: Arc::clone(&shared_state.database_unlocked),
+            gallery_regenerate_flag: Arc::clone(&shared_state.gallery_regenerate_flag),
+            dir_link_map: Arc::new(Mutex::new(HashMap::new())),
+            file_snapshots: HashMap::new(),
+            attempted_files: HashSet::new(),
+            last_error: None,
+        };
+        thread::spawn(move || loop {
+            match watcher.poll() {
+                Ok(()) => watcher.last_error = None,
+                Err(e) => {
+                    let error = e.to_string();
+                    if watcher.last_error.as_ref() != Some(&error) {
+                        ui::toast_error_lock(&watcher.toasts, format!("folder watcher failed: {error}"));
+                    }
+                    watcher.last_error = Some(error);
+                }
+            }
+            thread::sleep(Duration::from_secs(Config::global().import.watch_interval.max(1)));
+        });
+    }
+
+    fn poll(&mut self) -> Result<()> {
+        let config = Config::clone();
+        if !config.import.watch_enabled || !SharedState::read_update_flag(&self.database_unlocked) {
+            return Ok(());
+        }
+        let extension_filter = config.import.watch_extension_filter();
+        // files moved out of the folders don't need remembering, and one put back under the same name is new
+        self.attempted_files.retain(|path| path.exists());
+        let mut file_snapshots = HashMap::new();
+        let mut ready_entries = vec![];
+        for folder in config.import.watched_folders(&config.path)? {
+            if !folder.is_dir() {
+                continue;
+            }
+            // unreadable files are skipped, they're tried again on the next poll. attempted ones aren't read at all
+            let scan_result = scan_directory(folder.clone(), &extension_filter, &ScanProgress::default(), &self.attempted_files)?;
+            for import_entry in scan_result.import_entries {
+                let entry_path = import_entry.dir_entry.path();
+                if import_entry.is_archive {
+                    continue;
+                }
+                // it was moved or deleted since the scan
+                let metadata = match import_entry.dir_entry.metadata() {
+                    Ok(metadata) => metadata,
+                    Err(_) => continue,
+                };
+                let snapshot = (metadata.len(), metadata.modified().ok());
+                // a file is considered fully written once its size and modified time are unchanged between two polls
+                if self.file_snapshots.get(&entry_path) == Some(&snapshot) {
+                    ready_entries.push((folder.clone(), import_entry));
+                } else {
+                    file_snapshots.insert(entry_path, snapshot);
+                }
+            }
+        }
+        self.file_snapshots = file_snapshots;
+
+        if ready_entries.is_empty() {
+            return Ok(());
+        }
+
+        let (mut imported_count, mut duplicate_count, mut deleted_count, mut failed_count) = (0, 0, 0, 0);
+        // each watched folder's files of this poll make up one import session
+        let mut session_ids: HashMap<PathBuf, i64> = HashMap::new();
+        for (folder, _import_entry) in &ready_entries {
+            if !session_ids.contains_key(folder) {
+                let files = ready_entries
+                    .iter()
+                    .filter(|(other_folder, _)| other_folder == folder)
+                    .map(|(_, import_entry)| ImportSessionFile {
+                        path: import_entry.dir_entry.path(),
+                        is_selected: true,
+                        status: None,
+                    })
+                    .collect();
+                session_ids.insert(folder.clone(), data::create_import_session(folder, &files)?);
+            }
+        }
+
+        let import_rules = compile_import_rules(&config.import.rules);
+        let mut session_files: HashMap<i64, Vec<ImportSessionFile>> = HashMap::new();
+        for batch in ready_entries.chunks_mut(WATCH_IMPORT_BATCH_SIZE) {
+            let mut reg_forms = vec![];
+            for (folder, import_entry) in batch.iter_mut() {
+                self.attempted_files.insert(import_entry.dir_entry.path());
+                let session_id = session_ids.get(folder).copied();
+                import_entry.load_bytes();
+                import_entry.bytes.as_ref().unwrap().block_until_ready();
+                if let Ok(reg_form) = import_entry.generate_reg_form(
+                    Arc::clone(&self.dir_link_map),
+                    Config::global().import.duplicate_action,
+                    session_id,
+                    &import_rules,
+                ) {
+                    reg_forms.push(reg_form);
+                }
+            }
+
+            let batch_succeeded = match data::register_media_with_forms(reg_forms) {
+                Ok(()) => true,
+                Err(e) => {
+                    ui::toast_error_lock(&self.toasts, format!("failed to import watched files: {e}"));
+                    false
+                }
+            };
+
+            for (folder, import_entry) in batch.iter_mut() {
+                let status = match import_entry.importation_status.take() {
+                    Some(promise) if batch_succeeded => promise.block_and_take(),
+                    _ => ImportationStatus::Fail(anyhow!("batch failed")),
+                };
+                match status {
+                    ImportationStatus::Success => imported_count += 1,
+                    ImportationStatus::Duplicate(_) => duplicate_count += 1,
+                    ImportationStatus::PreviouslyDeleted => deleted_count += 1,
+                    _ => failed_count += 1,
+                }
+                import_entry.bytes = None;
+                if config.import.watch_organize {
+                    if let Some(organize_rule) = config.import.organize_rule(&status) {
+                        if let Err(e) = organize_file(&import_entry.organize_paths(), organize_rule, folder) {
+                            ui::toast_error_lock(&self.toasts, format!("failed to organize watched file: {e}"));
+                        }
+                    }
+                }
+                if let Some(session_id) = session_ids.get(folder) {
+                    session_files.entry(*session_id).or_default().push(ImportSessionFile {
+                        path: import_entry.dir_entry.path(),
+                        is_selected: true,
+                        status: Some(status),
+                    });
+                }
+            }
+        }
+        for session_id in session_ids.values() {
+            let files = session_files.remove(session_id).unwrap_or_default();
+            data::update_import_session(*session_id, &files, &self.dir_link_map.lock())?;
+            data::finish_import_session(*session_id)?;
+        }
+
+        let summary = format!(
+            "watched folders: {imported_count} imported, {duplicate_count} duplicates, {deleted_count} previously deleted, {failed_count} failed"
+        );
+        if failed_count > 0 {
+            ui::toast_warning_lock(&self.toasts, summary);
+        } else {
+            ui::toast_success_lock(&self.toasts, summary);
+        }
+        if imported_count > 0 {
+            SharedState::raise_update_flag(&self.gallery_regenerate_flag);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool_members(file_labels: &[&str]) -> Vec<(Option<String>, String)> {
+        file_labels
+            .iter()
+            .map(|file_label| (Some(String::from("pool")), file_label.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn pool_positions_put_sequence_numbers_first() {
+        let members = pool_members(&["pool/page10.jpg", "pool/cover.jpg", "pool/page2.jpg"]);
+        let sequence_regex = compile_sequence_regex(r"page(\d+)").unwrap();
+        assert_eq!(pool_positions(&members, sequence_regex.as_ref()), vec![Some(2), Some(3), Some(1)]);
+    }
+
+    #[test]
+    fn pool_positions_fall_back_to_natural_order() {
+        let members = pool_members(&["pool/Page10.jpg", "pool/page02.jpg", "pool/page1.jpg"]);
+        assert_eq!(pool_positions(&members, None), vec![Some(3), Some(2), Some(1)]);
+    }
+
+    #[test]
+    fn pool_positions_are_counted_per_pool() {
+        let members = vec![
+            (Some(String::from("first")), String::from("first/2.jpg")),
+            (None, String::from("loose.jpg")),
+            (Some(String::from("second")), String::from("second/1.jpg")),
+            (Some(String::from("first")), String::from("first/1.jpg")),
+        ];
+        assert_eq!(pool_positions(&members, None), vec![Some(2), None, Some(1), Some(1)]);
+    }
+
+    #[test]
+    fn json_sidecar_rejects_malformed_json() {
+        assert!(parse_json_sidecar(r#"{"tags": ["blue_sky""#, 10).is_err());
+        assert!(parse_json_sidecar(r#"["blue_sky"]"#, 10).is_err());
+        assert!(parse_json_sidecar("", 10).is_err());
+    }
+
+    #[test]
+    fn json_sidecar_reads_tags_sources_and_score() {
+        let metadata = parse_json_sidecar(
+            r#"{"tags": "blue_sky long_hair", "tags_artist": ["some artist"], "source": "https://example.com/1", "score": 4}"#,
+            10,
+        )
+        .unwrap();
+        let tagstrings = metadata.tags.iter().map(|tag| tag.to_tagstring()).collect::<Vec<_>>();
+        assert_eq!(tagstrings, vec!["blue_sky", "long_hair", "artist::some_artist"]);
+        assert_eq!(metadata.sources, vec!["https://example.com/1"]);
+        assert_eq!(metadata.score, Some(4));
+    }
+
+    #[test]
+    fn json_sidecar_without_a_usable_score_leaves_it_unset() {
+        assert_eq!(parse_json_sidecar(r#"{"tags": ["blue_sky"]}"#, 10).unwrap().score, None);
+        assert_eq!(parse_json_sidecar(r#"{"score": 11}"#, 10).unwrap().score, None);
+        assert_eq!(parse_json_sidecar(r#"{"score": -1}"#, 10).unwrap().score, None);
+        assert_eq!(parse_json_sidecar(r#"{"score": "4"}"#, 10).unwrap().score, None);
+    }
+
+    #[test]
+    fn txt_sidecar_skips_comments_and_takes_urls_as_sources() {
+        let metadata = parse_txt_sidecar("# tags\nlong hair\n\nhttps://example.com/1\n");
+        let tagstrings = metadata.tags.iter().map(|tag| tag.to_tagstring()).collect::<Vec<_>>();
+        assert_eq!(tagstrings, vec!["long_hair"]);
+        assert_eq!(metadata.sources, vec!["https://example.com/1"]);
+    }
+
+    #[test]
+    fn sidecars_are_read_without_duplicate_tags_or_sources() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let sidecar_paths = vec![temp_dir.path().join("image.jpg.txt"), temp_dir.path().join("image.txt")];
+        fs::write(&sidecar_paths[0], "long hair\nlong_hair\nblue_sky\nhttps://example.com/1\n").unwrap();
+        fs::write(&sidecar_paths[1], "blue_sky\nhttps://example.com/1\n").unwrap();
+        let metadata = read_sidecar_metadata(&sidecar_paths).unwrap();
+        let tagstrings = metadata.tags.iter().map(|tag| tag.to_tagstring()).collect::<Vec<_>>();
+        assert_eq!(tagstrings, vec!["long_hair", "blue_sky"]);
+        assert_eq!(metadata.sources, vec!["https://example.com/1"]);
+    }
+
+    #[test]
+    fn invalid_sequence_regex_falls_back_to_natural_order() {
+        let sequence_regex = compile_sequence_regex("page(");
+        assert!(sequence_regex.is_err());
+        let members = pool_members(&["pool/page10.jpg", "pool/page9.jpg"]);
+        let sequence_regex = sequence_regex.ok().flatten();
+        assert_eq!(pool_positions(&members, sequence_regex.as_ref()), vec![Some(2), Some(1)]);
+        assert!(compile_sequence_regex("").unwrap().is_none());
+    }
+
+    fn folders(folder_names: &[&str]) -> Vec<String> {
+        folder_names.iter().map(|folder_name| folder_name.to_string()).collect()
+    }
+
+    fn import_config(pool_mapping: PoolMapping, pool_depth: usize, folder_tag_namespaces: &str) -> config::Import {
+        let mut import_config = Config::default().import;
+        import_config.pool_mapping = pool_mapping;
+        import_config.pool_depth = pool_depth;
+        import_config.folder_tag_namespaces = folder_tag_namespaces.to_string();
+        import_config
+    }
+
+    #[test]
+    fn depth_mapping_pools_by_the_folder_at_pool_depth() {
+        let folder_names = folders(&["comics", "issue 1", "scans"]);
+        let (linking_dir, tags) = map_folders(&folder_names, &import_config(PoolMapping::Depth, 2, ""));
+        assert_eq!(linking_dir, Some(String::from("comics/issue 1")));
+        assert!(tags.is_empty());
+        let (linking_dir, _) = map_folders(&folder_names, &import_config(PoolMapping::Depth, 3, ""));
+        assert_eq!(linking_dir, Some(String::from("comics/issue 1/scans")));
+    }
+
+    #[test]
+    fn depth_mapping_beyond_the_path_or_at_zero_has_no_pool() {
+        let folder_names = folders(&["comics", "issue 1"]);
+        assert_eq!(map_folders(&folder_names, &import_config(PoolMapping::Depth, 3, "")).0, None);
+        assert_eq!(map_folders(&folder_names, &import_config(PoolMapping::Depth, 0, "")).0, None);
+        assert_eq!(map_folders(&[], &import_config(PoolMapping::Depth, 1, "")).0, None);
+    }
+
+    #[test]
+    fn leaf_mapping_pools_by_the_whole_folder_path() {
+        let (linking_dir, tags) = map_folders(&folders(&["comics", "issue 1"]), &import_config(PoolMapping::Leaf, 1, ""));
+        assert_eq!(linking_dir, Some(String::from("comics/issue 1")));
+        assert!(tags.is_empty());
+        // files directly in the scanned folder aren't pooled
+        assert_eq!(map_folders(&[], &import_config(PoolMapping::Leaf, 1, "")).0, None);
+    }
+
+    #[test]
+    fn tag_mapping_namespaces_folders_by_depth() {
+        let folder_names = folders(&["some artist", "blue  sky", "extra"]);
+        let (linking_dir, tags) = map_folders(&folder_names, &import_config(PoolMapping::Tags, 1, "artist  series"));
+        assert_eq!(linking_dir, None);
+        let tagstrings = tags.iter().map(|tag| tag.to_tagstring()).collect::<Vec<_>>();
+        // folders deeper than the namespaces given go without one
+        assert_eq!(tagstrings, vec!["artist::some_artist", "series::blue_sky", "extra"]);
+    }
+
+    #[test]
+    fn ignore_rules_match_at_any_depth_unless_anchored() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join(IGNORE_FILE_NAME),
+            "# scratch files\n\n*.tmp\ndrafts/\n/raw/cover.jpg\n",
+        )
+        .unwrap();
+        let ignore_rules = IgnoreRules::read(temp_dir.path()).unwrap().unwrap();
+        let is_ignored = |relative_path: &str| ignore_rules.is_ignored(&temp_dir.path().join(relative_path));
+        assert!(is_ignored("page.tmp"));
+        assert!(is_ignored("issue 1/page.tmp"));
+        assert!(is_ignored("issue 1/drafts"));
+        assert!(is_ignored("raw/cover.jpg"));
+        assert!(!is_ignored("issue 1/raw/cover.jpg"));
+        assert!(!is_ignored("page.jpg"));
+        assert!(!is_ignored("# scratch files"));
+        // paths outside the folder the rules were read from are never ignored by them
+        assert!(!ignore_rules.is_ignored(Path::new("/elsewhere/page.tmp")));
+    }
+
+    #[test]
+    fn negated_ignore_patterns_are_skipped() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join(IGNORE_FILE_NAME), "*.tmp\n!keep.tmp\n!*.jpg\n").unwrap();
+        let ignore_rules = IgnoreRules::read(temp_dir.path()).unwrap().unwrap();
+        // a negation neither un-ignores what an earlier pattern matched nor ignores what it names
+        assert!(ignore_rules.is_ignored(&temp_dir.path().join("keep.tmp")));
+        assert!(!ignore_rules.is_ignored(&temp_dir.path().join("page.jpg")));
+    }
+
+    #[test]
+    fn folders_without_an_ignore_file_have_no_rules() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert!(IgnoreRules::read(temp_dir.path()).unwrap().is_none());
+        fs::write(temp_dir.path().join(IGNORE_FILE_NAME), "[unclosed\n").unwrap();
+        assert!(IgnoreRules::read(temp_dir.path()).is_err());
+    }
+
+    #[test]
+    fn percent_decode_decodes_escaped_bytes() {
+        assert_eq!(percent_decode("/home/me/my%20pictures/caf%C3%A9.png"), "/home/me/my pictures/café.png");
+        assert_eq!(percent_decode("%2f%2F"), "//");
+        assert_eq!(percent_decode("no escapes"), "no escapes");
+    }
+
+    #[test]
+    fn percent_decode_keeps_invalid_escapes_as_they_are() {
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%4"), "%4");
+        assert_eq!(percent_decode("%zz%g1"), "%zz%g1");
+        assert_eq!(percent_decode("%+1%-1"), "%+1%-1");
+        assert_eq!(percent_decode("%%41"), "%A");
+        assert_eq!(percent_decode("%é"), "%é");
+        // escapes that aren't valid utf-8 are replaced rather than failing the whole path
+        assert_eq!(percent_decode("a%FFb"), "a\u{FFFD}b");
+    }
+}
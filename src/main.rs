@@ -1,10 +1,13 @@
 #![allow(dead_code)]
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod archive;
 mod config;
 mod data;
+mod formats;
 mod gallery;
 mod import;
+mod similarity;
 mod tags;
 mod ui;
 mod util;
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+// a perceptual hash packed into an integer, so the distance between two is a xor and a popcount
+pub type HashBits = u64;
+
+/// A media's perceptual hash, in the form the similarity index searches
+#[derive(Debug, Clone, PartialEq)]
+pub enum PerceptualHash {
+    Image(HashBits),
+    // one hash per sampled frame, in order
+    Video(Vec<HashBits>),
+}
+
+// number of differing bits
+pub fn hamming_distance(bits: HashBits, other_bits: HashBits) -> u32 {
+    (bits ^ other_bits).count_ones()
+}
+
+// packs a hex encoded hash big endian, none if it's not hex or doesn't fit
+pub fn hash_bits_from_hex(hex_hash: &str) -> Option<HashBits> {
+    let bytes = hex::decode(hex_hash).ok()?;
+    if bytes.is_empty() || bytes.len() > std::mem::size_of::<HashBits>() {
+        return None;
+    }
+    Some(bytes.iter().fold(0, |bits, byte| (bits << 8) | *byte as HashBits))
+}
+
+// share of the shorter video's frames that need a close frame in the other for the two to match
+const VIDEO_MATCHED_SHARE: f32 = 0.5;
+
+// each frame of the shorter video is matched to its closest frame in the other, wherever it is, so a trimmed or
+// offset clip still lines up. the distance is the one `VIDEO_MATCHED_SHARE` of those matches are within, so frames
+// without a counterpart (an intro, a cut scene) don't drag the rest down the way they would an average
+pub fn video_distance(frames: &[HashBits], other_frames: &[HashBits]) -> Option<u32> {
+    let (shorter, longer) = if frames.len() <= other_frames.len() {
+        (frames, other_frames)
+    } else {
+        (other_frames, frames)
+    };
+    if shorter.is_empty() {
+        return None;
+    }
+    let mut closest_distances = shorter
+        .iter()
+        .map(|frame| longer.iter().map(|other_frame| hamming_distance(*frame, *other_frame)).min())
+        .collect::<Option<Vec<_>>>()?;
+    closest_distances.sort_unstable();
+    let matched_count = (closest_distances.len() as f32 * VIDEO_MATCHED_SHARE).ceil() as usize;
+    closest_distances.get(matched_count.max(1) - 1).copied()
+}
+
+// images and videos are never compared against each other
+pub fn distance(p_hash: &PerceptualHash, other_p_hash: &PerceptualHash) -> Option<u32> {
+    match (p_hash, other_p_hash) {
+        (PerceptualHash::Image(bits), PerceptualHash::Image(other_bits)) => Some(hamming_distance(*bits, *other_bits)),
+        (PerceptualHash::Video(frames), PerceptualHash::Video(other_frames)) => video_distance(frames, other_frames),
+        _ => None,
+    }
+}
+
+struct BkNode<T> {
+    bits: HashBits,
+    value: T,
+    // (distance to this node, index of the child)
+    children: Vec<(u32, usize)>,
+}
+
+/// A BK-tree over hamming distance. Finding everything within a small distance of a hash only visits the subtrees
+/// the triangle inequality can't rule out, rather than every hash
+pub struct BkTree<T> {
+    nodes: Vec<BkNode<T>>,
+}
+
+impl<T> Default for BkTree<T> {
+    fn default() -> Self {
+        Self { nodes: vec![] }
+    }
+}
+
+impl<T> BkTree<T> {
+    pub fn insert(&mut self, bits: HashBits, value: T) {
+        let new_index = self.nodes.len();
+        self.nodes.push(BkNode {
+            bits,
+            value,
+            children: vec![],
+        });
+        if new_index == 0 {
+            return;
+        }
+        let mut node_index = 0;
+        loop {
+            let node_distance = hamming_distance(self.nodes[node_index].bits, bits);
+            match self.nodes[node_index]
+                .children
+                .iter()
+                .find(|(child_distance, _)| *child_distance == node_distance)
+            {
+                Some((_, child_index)) => node_index = *child_index,
+                None => {
+                    self.nodes[node_index].children.push((node_distance, new_index));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Everything within `max_distance` bits of `bits`, with its distance
+    pub fn find_within(&self, bits: HashBits, max_distance: u32) -> Vec<(&T, u32)> {
+        let mut found = vec![];
+        if self.nodes.is_empty() {
+            return found;
+        }
+        let mut pending = vec![0];
+        while let Some(node_index) = pending.pop() {
+            let node = &self.nodes[node_index];
+            let node_distance = hamming_distance(node.bits, bits);
+            if node_distance <= max_distance {
+                found.push((&node.value, node_distance));
+            }
+            let (min_distance, max_child_distance) = (node_distance.saturating_sub(max_distance), node_distance + max_distance);
+            pending.extend(
+                node.children
+                    .iter()
+                    .filter(|(child_distance, _)| (min_distance..=max_child_distance).contains(child_distance))
+                    .map(|(_, child_index)| *child_index),
+            );
+        }
+        found
+    }
+}
+
+/// Every media's perceptual hash, searchable by distance. Images and video frames are kept in separate trees
+#[derive(Default)]
+pub struct SimilarityIndex {
+    image_tree: BkTree<String>,
+    video_frame_tree: BkTree<String>,
+    p_hashes: HashMap<String, PerceptualHash>,
+}
+
+impl SimilarityIndex {
+    pub fn insert(&mut self, hash: String, p_hash: PerceptualHash) {
+        match &p_hash {
+            PerceptualHash::Image(bits) => self.image_tree.insert(*bits, hash.clone()),
+            PerceptualHash::Video(frames) => {
+                for frame in frames {
+                    self.video_frame_tree.insert(*frame, hash.clone());
+                }
+            }
+        }
+        self.p_hashes.insert(hash, p_hash);
+    }
+
+    /// The media within `max_distance` of `p_hash` and their distances, closest first
+    pub fn find_within(&self, p_hash: &PerceptualHash, max_distance: u32) -> Vec<(&String, u32)> {
+        let mut found = match p_hash {
+            PerceptualHash::Image(bits) => self.image_tree.find_within(*bits, max_distance),
+            PerceptualHash::Video(frames) => {
+                // enough frames matching within the distance means at least one frame pair is within it, so only videos
+                // sharing a close frame need comparing in full
+                let mut candidates = frames
+                    .iter()
+                    .flat_map(|frame| self.video_frame_tree.find_within(*frame, max_distance))
+                    .map(|(hash, _)| hash)
+                    .collect::<Vec<_>>();
+                candidates.sort();
+                candidates.dedup();
+                candidates
+                    .into_iter()
+                    .filter_map(|hash| {
+                        let video_distance = distance(p_hash, self.p_hashes.get(hash)?)?;
+                        (video_distance <= max_distance).then_some((hash, video_distance))
+                    })
+                    .collect()
+            }
+        };
+        found.sort_by_key(|(_, distance)| *distance);
+        found
+    }
+
+    /// The media within `max_distance` of the stored media `hash`, not counting itself
+    pub fn neighbours(&self, hash: &String, max_distance: u32) -> Vec<(&String, u32)> {
+        match self.p_hashes.get(hash) {
+            Some(p_hash) => self
+                .find_within(p_hash, max_distance)
+                .into_iter()
+                .filter(|(other_hash, _)| *other_hash != hash)
+                .collect(),
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // deterministic bits, so a failure can be reproduced
+    fn pseudo_random_bits(count: usize) -> Vec<HashBits> {
+        let mut state: HashBits = 0x9e37_79b9_7f4a_7c15;
+        (0..count)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bk_tree_finds_the_same_as_a_full_scan() {
+        let all_bits = pseudo_random_bits(500);
+        let mut tree = BkTree::default();
+        for (index, bits) in all_bits.iter().enumerate() {
+            tree.insert(*bits, index);
+            // near copies, so small distances have something to find
+            tree.insert(*bits ^ 0b1011, index + all_bits.len());
+        }
+        let all_values = all_bits
+            .iter()
+            .enumerate()
+            .flat_map(|(index, bits)| [(index, *bits), (index + all_bits.len(), *bits ^ 0b1011)])
+            .collect::<Vec<_>>();
+        for query in all_bits.iter().step_by(25) {
+            for max_distance in [0, 3, 10, 28, 64] {
+                let mut found = tree
+                    .find_within(*query, max_distance)
+                    .into_iter()
+                    .map(|(value, distance)| (*value, distance))
+                    .collect::<Vec<_>>();
+                found.sort_unstable();
+                let mut expected = all_values
+                    .iter()
+                    .map(|(value, bits)| (*value, hamming_distance(*bits, *query)))
+                    .filter(|(_, distance)| *distance <= max_distance)
+                    .collect::<Vec<_>>();
+                expected.sort_unstable();
+                assert_eq!(found, expected, "max distance {max_distance}");
+            }
+        }
+    }
+
+    #[test]
+    fn hash_bits_from_hex_packs_big_endian() {
+        assert_eq!(hash_bits_from_hex("ff"), Some(0xff));
+        assert_eq!(hash_bits_from_hex("0102"), Some(0x0102));
+        assert_eq!(hash_bits_from_hex("ffffffffffffffff"), Some(HashBits::MAX));
+        assert_eq!(hash_bits_from_hex(""), None);
+        assert_eq!(hash_bits_from_hex("not hex"), None);
+        assert_eq!(hash_bits_from_hex("010203040506070809"), None);
+    }
+
+    #[test]
+    fn video_distance_ignores_unmatched_frames() {
+        let frames = pseudo_random_bits(8);
+        assert_eq!(video_distance(&frames, &frames), Some(0));
+        assert_eq!(video_distance(&[], &frames), None);
+        // a trimmed clip matches wherever its frames are in the full video
+        assert_eq!(video_distance(&frames[2..6], &frames), Some(0));
+        // half the frames replaced still leaves the closest half exact
+        let mut half_replaced = frames.clone();
+        for frame in half_replaced.iter_mut().take(4) {
+            *frame = !*frame;
+        }
+        assert_eq!(video_distance(&half_replaced, &frames), Some(0));
+        // with most of them replaced, the distance is that of the replaced frames
+        let mut mostly_replaced = frames.clone();
+        for frame in mostly_replaced.iter_mut().take(5) {
+            *frame = !*frame;
+        }
+        assert!(video_distance(&mostly_replaced, &frames).unwrap() > 0);
+    }
+}
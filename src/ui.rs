@@ -41,6 +41,7 @@ pub type ToastsRef = Arc<Mutex<Toasts>>;
 pub mod config_ui;
 pub mod data_ui;
 pub mod debug_ui;
+pub mod duplicates_ui;
 pub mod gallery_ui;
 pub mod import_ui;
 pub mod preview_ui;
@@ -105,6 +106,7 @@ pub mod constants {
     pub const TAGS_TITLE: &str = "tags";
     pub const CONFIG_TITLE: &str = "config";
     pub const DATA_TITLE: &str = "data";
+    pub const DUPLICATES_TITLE: &str = "duplicates";
     pub const DEBUG_TITLE: &str = "debug";
 
     pub const DISABLED_LABEL_LOCKED_DATABASE: &str = "database is locked";
@@ -829,6 +831,12 @@ impl App {
 
                 title: icon!(constants::DATA_TITLE, DATA_ICON),
             },
+            WindowContainer {
+                window: Box::new(duplicates_ui::DuplicatesUI::new(&self.shared_state)),
+                is_open: None,
+
+                title: icon!(constants::DUPLICATES_TITLE, DUPLICATE_ICON),
+            },
             WindowContainer {
                 window: Box::new(debug_ui::DebugUI::new(&self.shared_state)),
                 is_open: Some(false),
@@ -3,7 +3,8 @@ use std::{fmt::Display, rc::Rc, sync::{atomic::Ordering}};
 use super::{
     widgets::autocomplete::{self, AutocompleteOption}, UserInterface,
 };
-use crate::{config::Config, app::SharedState};
+use crate::{config::{Config, ExtensionGroup, ImportRule, OrganizeAction, PoolMapping}, app::SharedState, data::DuplicateAction, formats};
+use crate::import;
 use crate::ui;
 use crate::ui::icon;
 use egui::{Align, DragValue, Grid, Layout, Response, Ui};
@@ -21,6 +22,7 @@ enum ConfigSection {
     #[default]
     Paths,
     General,
+    Import,
     Ui,
     Misc,
     Themes,
@@ -30,6 +32,7 @@ impl Display for ConfigSection {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ConfigSection::General => write!(f, "{}", icon!("general", CONFIG_ICON)),
+            ConfigSection::Import => write!(f, "{}", icon!("import", IMPORT_ICON)),
             ConfigSection::Ui => write!(f, "{}", icon!("ui", FONT_ICON)),
             ConfigSection::Paths => write!(f, "{}", icon!("paths", FOLDER_ICON)),
             ConfigSection::Misc => write!(f, "{}", icon!("misc", MISC_ICON)),
@@ -88,6 +91,231 @@ impl ConfigUI {
                     ui.end_row();
                 });
             }
+            ConfigSection::Import => {
+                let mut list_changed = false;
+                Grid::new("import_config").num_columns(2).show(ui, |ui| {
+                    ui.label("pool sequence regex");
+                    hook(ui.text_edit_singleline(&mut self.config_copy.import.sequence_regex)).on_hover_text(
+                        "picks the page number out of a file path when ordering pools, e.g. p(\\d+)\nleave empty to use natural sort only",
+                    );
+                    ui.end_row();
+                    ui.label("on duplicates");
+                    ui.horizontal(|ui| {
+                        for duplicate_action in DuplicateAction::ALL {
+                            hook(ui.selectable_value(
+                                &mut self.config_copy.import.duplicate_action,
+                                duplicate_action,
+                                duplicate_action.to_string(),
+                            ));
+                        }
+                    });
+                    ui.end_row();
+                    ui.label("check for near duplicates");
+                    hook(ui.checkbox(&mut self.config_copy.import.near_duplicate_check, "after scanning"));
+                    ui.end_row();
+                    ui.label("near duplicate distance");
+                    hook(ui.add(DragValue::new(&mut self.config_copy.import.near_duplicate_distance).clamp_range(0..=32)))
+                        .on_hover_text("how many bits two perceptual hashes may differ by");
+                    ui.end_row();
+                    let organize_rules = [
+                        ("after importing", &mut self.config_copy.import.organize_imported),
+                        ("after duplicates", &mut self.config_copy.import.organize_duplicate),
+                        ("after failures", &mut self.config_copy.import.organize_failed),
+                    ];
+                    for (label, organize_rule) in organize_rules {
+                        ui.label(label);
+                        ui.horizontal(|ui| {
+                            for organize_action in OrganizeAction::ALL {
+                                hook(ui.selectable_value(&mut organize_rule.action, organize_action, organize_action.to_string()));
+                            }
+                            ui.add_enabled_ui(organize_rule.action == OrganizeAction::Move, |ui| {
+                                hook(ui.text_edit_singleline(&mut organize_rule.move_to))
+                                    .on_hover_text("folder to move into, relative to the scanned folder");
+                            });
+                        });
+                        ui.end_row();
+                    }
+                    ui.label("watch folders");
+                    hook(ui.checkbox(&mut self.config_copy.import.watch_enabled, "enabled"));
+                    ui.end_row();
+                    ui.label("watch interval (seconds)");
+                    hook(ui.add(DragValue::new(&mut self.config_copy.import.watch_interval).clamp_range(1..=3600)));
+                    ui.end_row();
+                    ui.label("organize watched files");
+                    hook(ui.checkbox(&mut self.config_copy.import.watch_organize, ""));
+                    ui.end_row();
+                    ui.label("watched extensions");
+                    hook(ui.text_edit_singleline(&mut self.config_copy.import.watch_extensions));
+                    ui.end_row();
+                    ui.label("skip deleted media");
+                    hook(ui.checkbox(&mut self.config_copy.import.skip_deleted, ""))
+                        .on_hover_text("don't import files that were deleted from the library before, see the data tab to forget them");
+                    ui.end_row();
+                    ui.label("folders");
+                    ui.horizontal(|ui| {
+                        for pool_mapping in PoolMapping::ALL {
+                            hook(ui.selectable_value(&mut self.config_copy.import.pool_mapping, pool_mapping, pool_mapping.to_string()));
+                        }
+                        match self.config_copy.import.pool_mapping {
+                            PoolMapping::Depth => {
+                                hook(ui.add(DragValue::new(&mut self.config_copy.import.pool_depth).clamp_range(1..=16)))
+                                    .on_hover_text("1 pools the folders directly in the scanned folder");
+                            }
+                            PoolMapping::Leaf => (),
+                            PoolMapping::Tags => {
+                                hook(ui.text_edit_singleline(&mut self.config_copy.import.folder_tag_namespaces))
+                                    .on_hover_text("namespaces for each depth of folder, like \"artist series\"");
+                            }
+                        }
+                    });
+                    ui.end_row();
+                    ui.label("scan hidden files");
+                    hook(ui.checkbox(&mut self.config_copy.import.scan_hidden_files, ""))
+                        .on_hover_text(format!("files and folders can also be skipped with a {} file", import::IGNORE_FILE_NAME));
+                    ui.end_row();
+                    ui.label("extension groups");
+                    ui.vertical(|ui| {
+                        let mut removed_index = None;
+                        for (index, extension_group) in self.config_copy.import.extension_groups.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                hook(ui.add(egui::TextEdit::singleline(&mut extension_group.name).desired_width(60.)));
+                                hook(ui.text_edit_singleline(&mut extension_group.extensions));
+                                if ui.button(ui::constants::REMOVE_ICON).clicked() {
+                                    removed_index = Some(index);
+                                }
+                            });
+                            let unsupported_extensions = extension_group
+                                .extension_list()
+                                .into_iter()
+                                .filter(|ext| !formats::is_supported_extension(ext))
+                                .collect::<Vec<_>>();
+                            if !unsupported_extensions.is_empty() {
+                                ui.label(icon!(format!("unsupported: {}", unsupported_extensions.join(" ")), WARNING_ICON));
+                            }
+                        }
+                        if let Some(removed_index) = removed_index {
+                            self.config_copy.import.extension_groups.remove(removed_index);
+                            list_changed = true;
+                        }
+                        if ui.button(icon!("add group", ADD_ICON)).clicked() {
+                            self.config_copy.import.extension_groups.push(ExtensionGroup {
+                                name: String::from("new group"),
+                                extensions: String::new(),
+                            });
+                            list_changed = true;
+                        }
+                    });
+                    ui.end_row();
+                    ui.label("extra watched folders");
+                    ui.vertical(|ui| {
+                        let mut removed_index = None;
+                        for (index, folder) in self.config_copy.import.watch_folders.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                hook(ui.text_edit_singleline(folder));
+                                if ui.button(ui::constants::REMOVE_ICON).clicked() {
+                                    removed_index = Some(index);
+                                }
+                            });
+                        }
+                        if let Some(removed_index) = removed_index {
+                            self.config_copy.import.watch_folders.remove(removed_index);
+                            list_changed = true;
+                        }
+                        if ui.button(icon!("add folder", ADD_ICON)).clicked() {
+                            if let Some(path_buf) = rfd::FileDialog::new().pick_folder() {
+                                self.config_copy.import.watch_folders.push(path_buf.to_string_lossy().to_string());
+                                list_changed = true;
+                            }
+                        }
+                    });
+                    ui.end_row();
+                });
+                ui.separator();
+                ui.label("import rules");
+                let mut removed_index = None;
+                for (index, rule) in self.config_copy.import.rules.iter_mut().enumerate() {
+                    let title = if rule.name.is_empty() {
+                        format!("rule {}", index + 1)
+                    } else {
+                        rule.name.clone()
+                    };
+                    egui::CollapsingHeader::new(title).id_source(("import_rule", index)).show(ui, |ui| {
+                        Grid::new(("import_rule_config", index)).num_columns(2).show(ui, |ui| {
+                            ui.label("name");
+                            hook(ui.text_edit_singleline(&mut rule.name));
+                            ui.end_row();
+                            ui.label("enabled");
+                            hook(ui.checkbox(&mut rule.is_enabled, ""));
+                            ui.end_row();
+                            ui.label("source path regex");
+                            hook(ui.text_edit_singleline(&mut rule.path_regex));
+                            ui.end_row();
+                            ui.label("file name regex");
+                            hook(ui.text_edit_singleline(&mut rule.file_name_regex));
+                            ui.end_row();
+                            ui.label("archive name regex");
+                            hook(ui.text_edit_singleline(&mut rule.archive_name_regex));
+                            ui.end_row();
+                            ui.label("mime type prefix");
+                            hook(ui.text_edit_singleline(&mut rule.mime_prefix));
+                            ui.end_row();
+                            ui.label("width (min, max)");
+                            ui.horizontal(|ui| {
+                                hook(ui.add(DragValue::new(&mut rule.min_width)));
+                                hook(ui.add(DragValue::new(&mut rule.max_width)));
+                            });
+                            ui.end_row();
+                            ui.label("height (min, max)");
+                            ui.horizontal(|ui| {
+                                hook(ui.add(DragValue::new(&mut rule.min_height)));
+                                hook(ui.add(DragValue::new(&mut rule.max_height)));
+                            });
+                            ui.end_row();
+                            ui.label("add tags");
+                            hook(ui.text_edit_singleline(&mut rule.add_tags));
+                            ui.end_row();
+                            ui.label("set score");
+                            ui.horizontal(|ui| {
+                                let mut does_set_score = rule.set_score.is_some();
+                                if hook(ui.checkbox(&mut does_set_score, "")).changed() {
+                                    rule.set_score = does_set_score.then_some(0);
+                                }
+                                if let Some(score) = rule.set_score.as_mut() {
+                                    hook(ui.add(DragValue::new(score).clamp_range(0..=Config::global().general.entry_max_score)));
+                                }
+                            });
+                            ui.end_row();
+                            ui.label("bookmark");
+                            let mut does_set_bookmark = rule.set_bookmark.unwrap_or(false);
+                            if hook(ui.checkbox(&mut does_set_bookmark, "")).changed() {
+                                rule.set_bookmark = does_set_bookmark.then_some(true);
+                            }
+                            ui.end_row();
+                            ui.label("assign to pool");
+                            hook(ui.text_edit_singleline(&mut rule.assign_pool));
+                            ui.end_row();
+                        });
+                        if ui.add(ui::caution_button(icon!("remove rule", REMOVE_ICON))).clicked() {
+                            removed_index = Some(index);
+                        }
+                    });
+                }
+                if let Some(removed_index) = removed_index {
+                    self.config_copy.import.rules.remove(removed_index);
+                    list_changed = true;
+                }
+                if ui.button(icon!("add rule", ADD_ICON)).clicked() {
+                    self.config_copy.import.rules.push(ImportRule {
+                        is_enabled: true,
+                        ..Default::default()
+                    });
+                    list_changed = true;
+                }
+                // list edits don't come from a single response, so they can't go through the hook
+                if list_changed {
+                    config_changed = true;
+                }
+            }
             ConfigSection::Ui => {
                 Grid::new("ui_config").num_columns(2).show(ui, |ui| {
                     ui.label("thumbnail resolution");
@@ -1,9 +1,10 @@
 use super::{icon, toast_error_lock, toast_success_lock, toast_warning_lock, UserInterface};
 use crate::app::{SharedState, UpdateFlag};
-use crate::data::{DatabaseInfo, EntryId};
+use crate::data::{DatabaseInfo, DeletedHash, EntryId};
 use crate::ui;
 use crate::{config::Config, data};
 use anyhow::{anyhow, Result};
+use chrono::{TimeZone, Utc};
 use egui::{Align, Color32, Context, Label, Layout, ProgressBar, Rounding, Sense, TextEdit};
 use egui_extras::{Column, Size, StripBuilder, TableBuilder};
 use egui_modal::Modal;
@@ -19,18 +20,47 @@ pub struct DataUI {
     pub shared_state: Rc<SharedState>,
     pub currently_rekeying: UpdateFlag,
     takeout_progress: Option<Arc<TakeoutProgress>>,
-    duplicates: Option<Promise<Result<Vec<Vec<EntryId>>>>>,
+    // a library-wide job, which reports what it did when it's done
+    maintenance_job: Option<Promise<Result<String>>>,
+    deleted_hashes: Option<Promise<Result<Vec<DeletedHash>>>>,
+    is_deleted_hashes_window_open: bool,
     pub database_key: String,
     pub takeout_path: String,
+    pub takeout_as_archive: bool,
+    pub takeout_archive_key: String,
+    pub takeout_import_path: String,
+    pub takeout_import_key: String,
 }
 
 struct TakeoutProgress {
     current_index: AtomicUsize,
     total: AtomicUsize,
     cancel_flag: AtomicBool,
+    is_finished: AtomicBool,
+    is_import: bool,
     current_item: Arc<Mutex<String>>,
 }
 
+impl TakeoutProgress {
+    fn new(is_import: bool) -> Self {
+        Self {
+            current_index: AtomicUsize::new(0),
+            total: AtomicUsize::new(0),
+            cancel_flag: AtomicBool::new(false),
+            is_finished: AtomicBool::new(false),
+            is_import,
+            current_item: Arc::new(Mutex::new(String::new())),
+        }
+    }
+    // reports progress to the window, returns false if the takeout was cancelled
+    fn update(&self, index: usize, total: usize, entry_id: &EntryId) -> bool {
+        *self.current_item.lock() = ui::pretty_entry_id(entry_id);
+        self.current_index.store(index, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+        !self.cancel_flag.load(Ordering::Relaxed)
+    }
+}
+
 impl DataUI {
     pub fn new(shared_state: &Rc<SharedState>) -> Self {
         Self {
@@ -38,9 +68,15 @@ impl DataUI {
             database_info: None,
             database_key: String::new(),
             takeout_path: String::new(),
+            takeout_as_archive: false,
+            takeout_archive_key: String::new(),
+            takeout_import_path: String::new(),
+            takeout_import_key: String::new(),
             currently_rekeying: Arc::new(AtomicBool::new(false)),
             takeout_progress: None,
-            duplicates: None,
+            maintenance_job: None,
+            deleted_hashes: None,
+            is_deleted_hashes_window_open: false,
         }
     }
 }
@@ -51,6 +87,7 @@ impl UserInterface for DataUI {
     fn ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
         self.process_database_info();
         self.render_takeout_progress(ctx);
+        self.render_deleted_hashes_window(ctx);
         StripBuilder::new(ui)
             .size(Size::exact(0.)) // FIXME: not sure why this is adding more space.
             .size(Size::exact(ui::constants::OPTIONS_COLUMN_WIDTH))
@@ -130,6 +167,7 @@ impl DataUI {
     fn render_options(&mut self, ui: &mut egui::Ui, ctx: &Context) {
         let rekey_modal = self.render_rekey_modal(ctx);
         let export_all_modal = self.render_takeout_modal(ctx);
+        let import_takeout_modal = self.render_takeout_import_modal(ctx);
         ui.with_layout(Layout::top_down_justified(Align::Center), |ui| {
             ui.label("data");
             if ui.button(icon!("refresh", REFRESH_ICON)).clicked() {
@@ -155,12 +193,121 @@ impl DataUI {
             if ui.button(icon!("takeout", EXPORT_ICON)).clicked() {
                 export_all_modal.open();
             }
+            if ui.button(icon!("import takeout", IMPORT_ICON)).clicked() {
+                import_takeout_modal.open();
+            }
             ui::space(ui);
-            if ui.button(icon!("deduplicate", DUPLICATE_ICON)).clicked() {
-                dbg!(data::find_duplicates());
+            if ui
+                .button(icon!("deduplicate", DUPLICATE_ICON))
+                .on_hover_text("review groups of similar media in the duplicates tab")
+                .clicked()
+            {
+                SharedState::raise_update_flag(&self.shared_state.duplicates_request);
+            }
+            let is_job_running = self.maintenance_job.is_some();
+            if ui
+                .add_enabled(!is_job_running, egui::Button::new(icon!("fix media types", REFRESH_ICON)))
+                .on_hover_text("check every media's contents and correct types taken from a wrong extension")
+                .clicked()
+            {
+                self.maintenance_job = Some(Promise::spawn_thread("correct_media_mimes", || {
+                    data::correct_media_mimes().map(|corrected_count| match corrected_count {
+                        0 => String::from("every media type was already correct"),
+                        corrected_count => format!("corrected the type of {corrected_count} media"),
+                    })
+                }));
             }
+            if ui
+                .add_enabled(!is_job_running, egui::Button::new(icon!("hash videos", MOVIE_ICON)))
+                .on_hover_text("sample frames from videos that have no perceptual hash yet, so they can be matched as duplicates")
+                .clicked()
+            {
+                self.maintenance_job = Some(Promise::spawn_thread("backfill_video_perceptual_hashes", || {
+                    data::backfill_video_perceptual_hashes().map(|hashed_count| format!("hashed {hashed_count} videos"))
+                }));
+            }
+            if ui
+                .button(icon!("deleted media", DELETE_ICON))
+                .on_hover_text("media deleted from the library, which importing skips")
+                .clicked()
+            {
+                self.is_deleted_hashes_window_open = !self.is_deleted_hashes_window_open;
+                if self.is_deleted_hashes_window_open {
+                    self.load_deleted_hashes();
+                }
+            }
+            self.process_maintenance_job();
         });
     }
+    fn load_deleted_hashes(&mut self) {
+        self.deleted_hashes = Some(Promise::spawn_thread("load_deleted_hashes", || data::get_deleted_hashes()));
+    }
+    fn render_deleted_hashes_window(&mut self, ctx: &Context) {
+        let mut forgotten_hash = None;
+        let mut clear_all = false;
+        let deleted_hashes = self.deleted_hashes.as_ref().and_then(|promise| promise.ready());
+        egui::Window::new("deleted media")
+            .open(&mut self.is_deleted_hashes_window_open)
+            .show(ctx, |ui| match deleted_hashes {
+                None => {
+                    ui.spinner();
+                }
+                Some(Err(e)) => {
+                    ui.label(format!("failed to load deleted media: {e}"));
+                }
+                Some(Ok(deleted_hashes)) => {
+                    ui.label(format!("{} deleted media are skipped when importing", deleted_hashes.len()));
+                    if ui
+                        .add_enabled(!deleted_hashes.is_empty(), ui::caution_button(icon!("forget all", DELETE_ICON)))
+                        .clicked()
+                    {
+                        clear_all = true;
+                    }
+                    ui.separator();
+                    egui::ScrollArea::vertical().auto_shrink([false, true]).show(ui, |ui| {
+                        egui::Grid::new("deleted_hashes").striped(true).show(ui, |ui| {
+                            for deleted_hash in deleted_hashes {
+                                ui.label(&deleted_hash.hash[..deleted_hash.hash.len().min(16)])
+                                    .on_hover_text(&deleted_hash.hash);
+                                let date_deleted = Utc
+                                    .timestamp_opt(deleted_hash.date_deleted, 0)
+                                    .single()
+                                    .map(|datetime| datetime.format("%B %e, %Y").to_string())
+                                    .unwrap_or_default();
+                                ui.label(date_deleted);
+                                ui.label(deleted_hash.reason.as_deref().unwrap_or(""));
+                                if ui.button("forget").on_hover_text("allow importing it again").clicked() {
+                                    forgotten_hash = Some(deleted_hash.hash.clone());
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+            });
+        let result = match (forgotten_hash, clear_all) {
+            (Some(hash), _) => Some(data::forget_deleted_hash(&hash)),
+            (None, true) => Some(data::clear_deleted_hashes()),
+            (None, false) => None,
+        };
+        match result {
+            Some(Ok(())) => self.load_deleted_hashes(),
+            Some(Err(e)) => toast_error_lock(&self.shared_state.toasts, format!("failed to forget deleted media: {e}")),
+            None => (),
+        }
+    }
+    fn process_maintenance_job(&mut self) {
+        if let Some(result) = self.maintenance_job.as_ref().and_then(|promise| promise.ready()) {
+            match result {
+                Ok(summary) => {
+                    toast_success_lock(&self.shared_state.toasts, summary);
+                    SharedState::raise_update_flag(&self.shared_state.gallery_regenerate_flag);
+                }
+                Err(e) => toast_error_lock(&self.shared_state.toasts, format!("maintenance job failed: {e}")),
+            }
+            self.maintenance_job = None;
+        }
+    }
     fn render_data_info(&mut self, ui: &mut egui::Ui, ctx: &Context) {
         match self.database_info.as_ref().and_then(|p| p.ready()) {
             Some(Ok(database_info)) => {
@@ -296,14 +443,19 @@ impl DataUI {
             let current = takeout_progress.current_index.load(Ordering::Relaxed) + 1;
             let total = takeout_progress.total.load(Ordering::Relaxed);
             let progress = current as f32 / total as f32;
+            let (current_verb, done_verb) = if takeout_progress.is_import {
+                ("importing", "imported")
+            } else {
+                ("exporting", "exported")
+            };
             egui::Window::new("takeout progress").show(ctx, |ui| {
-                if progress != 1. {
+                if !takeout_progress.is_finished.load(Ordering::Relaxed) {
                     let current_item = if let Some(current_item) = takeout_progress.current_item.try_lock() {
                         current_item.clone()
                     } else {
                         String::from("[ ? ]")
                     };
-                    ui.label(format!("exporting {current_item}..."));
+                    ui.label(format!("{current_verb} {current_item}..."));
                     ui.add(ProgressBar::new(progress).animate(true).text(format!("{current} / {total}")));
                     ui.separator();
                     ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
@@ -313,9 +465,17 @@ impl DataUI {
                     });
                 } else {
                     ui.vertical_centered_justified(|ui| {
-                        ui.label(icon!(format!("{total} items exported"), SUCCESS_ICON));
-                        if ui.button(icon!("open takeout folder", OPEN_ICON)).clicked() {
-                            if let Err(e) = open::that(&self.takeout_path) {
+                        if takeout_progress.cancel_flag.load(Ordering::Relaxed) {
+                            ui.label(icon!("takeout cancelled", WARNING_ICON));
+                        } else {
+                            ui.label(icon!(format!("{total} items {done_verb}"), SUCCESS_ICON));
+                        }
+                        if !takeout_progress.is_import && ui.button(icon!("open takeout folder", OPEN_ICON)).clicked() {
+                            let mut open_path = PathBuf::from(&self.takeout_path);
+                            if open_path.is_file() {
+                                open_path.pop();
+                            }
+                            if let Err(e) = open::that(&open_path) {
                                 ui::toast_error_lock(&self.shared_state.toasts, format!("failed to open path: {e}"));
                             }
                         }
@@ -336,7 +496,11 @@ impl DataUI {
         ask_modal.show(|ui| {
             ask_modal.title(ui, icon!("export all items", EXPORT_ICON));
             ask_modal.frame(ui, |ui| {
-                ask_modal.body(ui, "export everything?\nchoose a path to export to");
+                if self.takeout_as_archive {
+                    ask_modal.body(ui, "export everything?\nchoose a file to save the encrypted archive to");
+                } else {
+                    ask_modal.body(ui, "export everything?\nchoose a path to export to");
+                }
                 let tedit_resp = ui.add(
                     TextEdit::singleline(&mut self.takeout_path)
                         .hint_text("click to set path")
@@ -345,38 +509,71 @@ impl DataUI {
                 );
 
                 if ui.interact(tedit_resp.rect, tedit_resp.id.with("click_sense"), Sense::click()).clicked() {
-                    if let Some(path_buf) = rfd::FileDialog::new().pick_folder() {
+                    let path_opt = if self.takeout_as_archive {
+                        rfd::FileDialog::new()
+                            .add_filter("takeout archive", &[data::TAKEOUT_ARCHIVE_EXTENSION])
+                            .set_file_name(&format!("htool.{}", data::TAKEOUT_ARCHIVE_EXTENSION))
+                            .save_file()
+                    } else {
+                        rfd::FileDialog::new().pick_folder()
+                    };
+                    if let Some(path_buf) = path_opt {
                         self.takeout_path = path_buf.as_path().to_string_lossy().to_string();
                     }
                 }
+                if ui.checkbox(&mut self.takeout_as_archive, "encrypted archive").changed() {
+                    self.takeout_path.clear();
+                }
+                if self.takeout_as_archive {
+                    ui.add(
+                        TextEdit::singleline(&mut self.takeout_archive_key)
+                            .password(true)
+                            .hint_text("archive key"),
+                    );
+                }
                 ask_modal.buttons(ui, |ui| {
                     ask_modal.button(ui, "cancel");
                     if ask_modal.suggested_button(ui, icon!("export all", EXPORT_ICON)).clicked() {
-                        self.takeout_progress = Some(Arc::new(TakeoutProgress {
-                            current_index: AtomicUsize::new(0),
-                            total: AtomicUsize::new(0),
-                            cancel_flag: AtomicBool::new(false),
-                            current_item: Arc::new(Mutex::new(String::new())),
-                        }));
+                        if self.takeout_as_archive && self.takeout_archive_key.is_empty() {
+                            toast_warning_lock(&self.shared_state.toasts, "an encrypted archive needs a key");
+                            return;
+                        }
+                        self.takeout_progress = Some(Arc::new(TakeoutProgress::new(false)));
                         let takeout_progress = Arc::clone(self.takeout_progress.as_ref().unwrap());
                         let toasts = Arc::clone(&self.shared_state.toasts);
                         let export_path = PathBuf::from(&self.takeout_path);
-                        thread::spawn(move || match data::get_all_entry_info() {
-                            Ok(mut entry_info) => {
-                                entry_info.retain(|e| e.details().is_independant);
-                                takeout_progress.total.store(entry_info.len(), Ordering::Relaxed);
-                                for (i, entry_info) in entry_info.iter().enumerate() {
-                                    if takeout_progress.cancel_flag.load(Ordering::Relaxed) {
-                                        break;
-                                    }
-                                    *takeout_progress.current_item.lock() = ui::pretty_entry_id(entry_info.entry_id());
-                                    takeout_progress.current_index.store(i, Ordering::Relaxed);
-                                    if let Err(e) = data::export_entry(entry_info.entry_id(), export_path.clone()) {
-                                        ui::toast_error_lock(&toasts, format!("failed to export {}: {e}", ui::pretty_entry_id(entry_info.entry_id())))
+                        let archive_key_opt = self.takeout_as_archive.then(|| self.takeout_archive_key.clone());
+                        thread::spawn(move || {
+                            match data::get_all_entry_info() {
+                                Ok(mut entry_info) => {
+                                    entry_info.retain(|e| e.details().is_independant);
+                                    if let Some(archive_key) = archive_key_opt {
+                                        let entry_ids = entry_info.iter().map(|e| e.entry_id().clone()).collect::<Vec<_>>();
+                                        if let Err(e) =
+                                            data::export_entries_to_archive(&entry_ids, &export_path, &archive_key, |i, total, entry_id| {
+                                                takeout_progress.update(i, total, entry_id)
+                                            })
+                                        {
+                                            ui::toast_error_lock(&toasts, format!("failed to export archive: {e}"))
+                                        }
+                                    } else {
+                                        let total = entry_info.len();
+                                        for (i, entry_info) in entry_info.iter().enumerate() {
+                                            if !takeout_progress.update(i, total, entry_info.entry_id()) {
+                                                break;
+                                            }
+                                            if let Err(e) = data::export_entry(entry_info.entry_id(), export_path.clone()) {
+                                                ui::toast_error_lock(
+                                                    &toasts,
+                                                    format!("failed to export {}: {e}", ui::pretty_entry_id(entry_info.entry_id())),
+                                                )
+                                            }
+                                        }
                                     }
                                 }
+                                Err(e) => ui::toast_error_lock(&toasts, format!("failed to load entry ids: {e}")),
                             }
-                            Err(e) => ui::toast_error_lock(&toasts, format!("failed to load entry ids: {e}")),
+                            takeout_progress.is_finished.store(true, Ordering::Relaxed);
                         });
                     }
                 });
@@ -384,6 +581,62 @@ impl DataUI {
         });
         ask_modal
     }
+    fn render_takeout_import_modal(&mut self, ctx: &Context) -> Modal {
+        let import_modal = Modal::new(ctx, "import_takeout_modal");
+        import_modal.show(|ui| {
+            import_modal.title(ui, icon!("import takeout", IMPORT_ICON));
+            import_modal.frame(ui, |ui| {
+                import_modal.body(ui, "choose an encrypted takeout archive to import");
+                let tedit_resp = ui.add(
+                    TextEdit::singleline(&mut self.takeout_import_path)
+                        .hint_text("click to set path")
+                        .interactive(false)
+                        .clip_text(false),
+                );
+                if ui.interact(tedit_resp.rect, tedit_resp.id.with("click_sense"), Sense::click()).clicked() {
+                    if let Some(path_buf) = rfd::FileDialog::new()
+                        .add_filter("takeout archive", &[data::TAKEOUT_ARCHIVE_EXTENSION])
+                        .pick_file()
+                    {
+                        self.takeout_import_path = path_buf.as_path().to_string_lossy().to_string();
+                    }
+                }
+                ui.add(TextEdit::singleline(&mut self.takeout_import_key).password(true).hint_text("archive key"));
+                import_modal.buttons(ui, |ui| {
+                    import_modal.button(ui, "cancel");
+                    if import_modal.suggested_button(ui, icon!("import", IMPORT_ICON)).clicked() {
+                        self.takeout_progress = Some(Arc::new(TakeoutProgress::new(true)));
+                        let takeout_progress = Arc::clone(self.takeout_progress.as_ref().unwrap());
+                        let toasts = Arc::clone(&self.shared_state.toasts);
+                        let gallery_regenerate_flag = Arc::clone(&self.shared_state.gallery_regenerate_flag);
+                        let tag_data_update_flag = Arc::clone(&self.shared_state.tag_data_update_flag);
+                        let archive_path = PathBuf::from(&self.takeout_import_path);
+                        let archive_key = self.takeout_import_key.clone();
+                        thread::spawn(move || {
+                            match data::import_entries_from_archive(&archive_path, &archive_key, |i, total, entry_id| {
+                                takeout_progress.update(i, total, entry_id)
+                            }) {
+                                Ok(summary) => {
+                                    ui::toast_success_lock(
+                                        &toasts,
+                                        format!(
+                                            "imported {} entries from takeout ({} already existed)",
+                                            summary.imported_count, summary.duplicate_count
+                                        ),
+                                    );
+                                    SharedState::raise_update_flag(&gallery_regenerate_flag);
+                                    SharedState::raise_update_flag(&tag_data_update_flag);
+                                }
+                                Err(e) => ui::toast_error_lock(&toasts, format!("failed to import takeout: {e}")),
+                            }
+                            takeout_progress.is_finished.store(true, Ordering::Relaxed);
+                        });
+                    }
+                });
+            });
+        });
+        import_modal
+    }
     fn render_rekey_modal(&mut self, ctx: &Context) -> Modal {
         let rekey_modal = Modal::new(ctx, "rekey_modal");
         rekey_modal.show(|ui| {
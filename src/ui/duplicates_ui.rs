@@ -0,0 +1,528 @@
+use super::{icon, toast_error_lock, toast_success_lock, UserInterface};
+use crate::app::SharedState;
+use crate::data::{self, DismissedDuplicate, DuplicateCandidate, EntryId};
+use crate::ui::{self, preview_ui::MediaPreview};
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use egui::{Align, Context, Layout, ScrollArea, Ui};
+use egui_extras::{Size, StripBuilder};
+use egui_modal::{Icon, Modal};
+use poll_promise::Promise;
+use std::rc::Rc;
+use std::sync::Arc;
+
+const CANDIDATE_COLUMN_WIDTH: f32 = 220.;
+
+pub struct DuplicatesUI {
+    shared_state: Rc<SharedState>,
+    // the hashes of each group of perceptually similar media
+    duplicate_groups: Option<Promise<Result<Vec<Vec<String>>>>>,
+    current_group_index: usize,
+    candidates: Option<Promise<Result<Vec<LoadedCandidate>>>>,
+    kept_index: usize,
+    merge_into_kept: bool,
+    // the hashes deleted by the resolution, which leave the group once it's done
+    resolving: Option<(Vec<String>, Promise<Result<()>>)>,
+    dismissing: Option<Promise<Result<()>>>,
+    dismissed_duplicates: Option<Promise<Result<Vec<DismissedDuplicate>>>>,
+    is_dismissed_window_open: bool,
+}
+
+struct LoadedCandidate {
+    candidate: DuplicateCandidate,
+    thumbnail: Option<Promise<Result<MediaPreview>>>,
+    // ticked to be deleted, or merged into the kept media
+    is_ticked: bool,
+}
+
+impl DuplicatesUI {
+    pub fn new(shared_state: &Rc<SharedState>) -> Self {
+        Self {
+            shared_state: Rc::clone(shared_state),
+            duplicate_groups: None,
+            current_group_index: 0,
+            candidates: None,
+            kept_index: 0,
+            merge_into_kept: true,
+            resolving: None,
+            dismissing: None,
+            dismissed_duplicates: None,
+            is_dismissed_window_open: false,
+        }
+    }
+
+    pub fn find_duplicates(&mut self) {
+        self.current_group_index = 0;
+        self.candidates = None;
+        self.duplicate_groups = Some(Promise::spawn_thread("find_duplicates", || {
+            data::find_duplicates().map(|duplicate_groups| {
+                duplicate_groups
+                    .into_iter()
+                    .map(|duplicate_group| {
+                        duplicate_group
+                            .into_iter()
+                            .filter_map(|(entry_id, _p_hash)| entry_id.as_media_entry_id().cloned())
+                            .collect()
+                    })
+                    .collect()
+            })
+        }));
+    }
+
+    fn get_duplicate_groups(&self) -> Option<&Vec<Vec<String>>> {
+        self.duplicate_groups
+            .as_ref()
+            .and_then(|promise| promise.ready())
+            .and_then(|result| result.as_ref().ok())
+    }
+
+    fn get_current_group(&self) -> Option<&Vec<String>> {
+        self.get_duplicate_groups()
+            .and_then(|duplicate_groups| duplicate_groups.get(self.current_group_index))
+    }
+
+    fn load_current_group(&mut self) {
+        let hashes = match self.get_current_group() {
+            Some(hashes) => hashes.clone(),
+            None => return,
+        };
+        self.kept_index = 0;
+        self.candidates = Some(Promise::spawn_thread("load_duplicate_candidates", move || {
+            let mut candidates = data::get_duplicate_candidates(&hashes)?;
+            // the largest comes first, so it's the one kept unless another is picked
+            candidates.sort_by_key(|candidate| {
+                let pixel_count = candidate.dimensions.map(|(width, height)| width as u64 * height as u64).unwrap_or(0);
+                std::cmp::Reverse((pixel_count, candidate.media_info.details.size))
+            });
+            Ok(candidates
+                .into_iter()
+                .map(|mut candidate| {
+                    let thumbnail = candidate
+                        .thumbnail
+                        .take()
+                        .map(|thumbnail| Promise::from_ready(ui::generate_retained_image(&thumbnail).map(MediaPreview::Picture)));
+                    LoadedCandidate {
+                        candidate,
+                        thumbnail,
+                        is_ticked: false,
+                    }
+                })
+                .collect())
+        }));
+    }
+
+    fn select_group(&mut self, group_index: usize) {
+        self.current_group_index = group_index;
+        self.candidates = None;
+        self.load_current_group();
+    }
+
+    fn process_state(&mut self) {
+        // the groups just finished loading
+        if self.candidates.is_none() && self.get_current_group().is_some() {
+            self.load_current_group();
+        }
+        if let Some((deleted_hashes, result)) = self
+            .resolving
+            .as_ref()
+            .and_then(|(deleted_hashes, promise)| promise.ready().map(|result| (deleted_hashes, result)))
+        {
+            match result {
+                Ok(()) => {
+                    toast_success_lock(&self.shared_state.toasts, "resolved duplicates");
+                    SharedState::raise_update_flag(&self.shared_state.gallery_regenerate_flag);
+                    SharedState::raise_update_flag(&self.shared_state.tag_data_update_flag);
+                    let deleted_hashes = deleted_hashes.clone();
+                    self.remove_from_current_group(&deleted_hashes);
+                }
+                Err(e) => toast_error_lock(&self.shared_state.toasts, format!("failed to resolve duplicates: {e}")),
+            }
+            self.resolving = None;
+        }
+        if let Some(result) = self.dismissing.as_ref().and_then(|promise| promise.ready()) {
+            match result {
+                Ok(()) => {
+                    toast_success_lock(&self.shared_state.toasts, "marked as not duplicates");
+                    self.remove_current_group();
+                    if self.is_dismissed_window_open {
+                        self.load_dismissed_duplicates();
+                    }
+                }
+                Err(e) => toast_error_lock(&self.shared_state.toasts, format!("failed to dismiss duplicates: {e}")),
+            }
+            self.dismissing = None;
+        }
+    }
+
+    // the group is gone, so the next one takes its index
+    fn remove_current_group(&mut self) {
+        if let Some(Ok(duplicate_groups)) = self.duplicate_groups.as_mut().and_then(|promise| promise.ready_mut()) {
+            if self.current_group_index < duplicate_groups.len() {
+                duplicate_groups.remove(self.current_group_index);
+            }
+            self.current_group_index = self.current_group_index.min(duplicate_groups.len().saturating_sub(1));
+        }
+        self.candidates = None;
+    }
+
+    // the rest of the group stays up for review, unless there's nothing left to compare
+    fn remove_from_current_group(&mut self, hashes: &[String]) {
+        let is_group_left = match self
+            .duplicate_groups
+            .as_mut()
+            .and_then(|promise| promise.ready_mut())
+            .and_then(|result| result.as_mut().ok())
+            .and_then(|duplicate_groups| duplicate_groups.get_mut(self.current_group_index))
+        {
+            Some(group) => {
+                group.retain(|hash| !hashes.contains(hash));
+                group.len() > 1
+            }
+            None => false,
+        };
+        if is_group_left {
+            self.candidates = None;
+        } else {
+            self.remove_current_group();
+        }
+    }
+
+    fn dismiss_current_group(&mut self) {
+        let hashes = match self.get_current_group() {
+            Some(hashes) => hashes.clone(),
+            None => return,
+        };
+        self.dismissing = Some(Promise::spawn_thread("dismiss_duplicate_group", move || {
+            data::dismiss_duplicate_group(&hashes)
+        }));
+    }
+
+    fn load_dismissed_duplicates(&mut self) {
+        self.dismissed_duplicates = Some(Promise::spawn_thread("load_dismissed_duplicates", data::get_dismissed_duplicates));
+    }
+
+    fn render_dismissed_window(&mut self, ctx: &Context) {
+        let mut undone_pair = None;
+        let mut undo_all = false;
+        let dismissed_duplicates = self.dismissed_duplicates.as_ref().and_then(|promise| promise.ready());
+        egui::Window::new("not duplicates")
+            .open(&mut self.is_dismissed_window_open)
+            .show(ctx, |ui| match dismissed_duplicates {
+                None => {
+                    ui.spinner();
+                }
+                Some(Err(e)) => {
+                    ui.label(format!("failed to load dismissed duplicates: {e}"));
+                }
+                Some(Ok(dismissed_duplicates)) => {
+                    ui.label(format!("{} pairs are never grouped as duplicates", dismissed_duplicates.len()));
+                    if ui
+                        .add_enabled(!dismissed_duplicates.is_empty(), ui::caution_button(icon!("undo all", REFRESH_ICON)))
+                        .clicked()
+                    {
+                        undo_all = true;
+                    }
+                    ui.separator();
+                    ScrollArea::vertical().auto_shrink([false, true]).show(ui, |ui| {
+                        egui::Grid::new("dismissed_duplicates").striped(true).show(ui, |ui| {
+                            for dismissed_duplicate in dismissed_duplicates {
+                                for hash in [&dismissed_duplicate.hash, &dismissed_duplicate.other_hash] {
+                                    ui.label(&hash[..hash.len().min(16)]).on_hover_text(hash);
+                                }
+                                let date_dismissed = Utc
+                                    .timestamp_opt(dismissed_duplicate.date_dismissed, 0)
+                                    .single()
+                                    .map(|datetime| datetime.format("%B %e, %Y").to_string())
+                                    .unwrap_or_default();
+                                ui.label(date_dismissed);
+                                if ui.button("undo").on_hover_text("allow grouping them as duplicates again").clicked() {
+                                    undone_pair = Some((dismissed_duplicate.hash.clone(), dismissed_duplicate.other_hash.clone()));
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+            });
+        if undone_pair.is_some() || undo_all {
+            let toasts = Arc::clone(&self.shared_state.toasts);
+            // the pairs are reloaded once the undo is done
+            self.dismissed_duplicates = Some(Promise::spawn_thread("undo_dismissed_duplicates", move || {
+                let result = match undone_pair {
+                    Some((hash, other_hash)) => data::undo_dismissed_duplicate(&hash, &other_hash),
+                    None => data::clear_dismissed_duplicates(),
+                };
+                if let Err(e) = result {
+                    ui::toast_error_lock(&toasts, format!("failed to undo dismissed duplicates: {e}"));
+                }
+                data::get_dismissed_duplicates()
+            }));
+        }
+    }
+
+    fn get_ticked_hashes(&self) -> Vec<String> {
+        match self.candidates.as_ref().and_then(|promise| promise.ready()) {
+            Some(Ok(loaded_candidates)) => loaded_candidates
+                .iter()
+                .enumerate()
+                .filter(|(index, loaded_candidate)| loaded_candidate.is_ticked && *index != self.kept_index)
+                .filter_map(|(_index, loaded_candidate)| loaded_candidate.candidate.media_info.details.id.as_media_entry_id().cloned())
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn render_resolve_modal(&mut self, ctx: &Context) -> Modal {
+        let modal = ui::modal(ctx, "resolve_duplicates");
+        modal.show(|ui| {
+            let ticked_count = self.get_ticked_hashes().len();
+            modal.title(ui, "resolve duplicates");
+            modal.frame(ui, |ui| {
+                let text = if self.merge_into_kept {
+                    format!("merge the {ticked_count} ticked media into the kept one, then delete them? this can't be undone.")
+                } else {
+                    format!("delete the {ticked_count} ticked media? this can't be undone.")
+                };
+                modal.body_and_icon(ui, text, Icon::Warning);
+            });
+            modal.buttons(ui, |ui| {
+                modal.button(ui, "cancel");
+                if modal.caution_button(ui, if self.merge_into_kept { "merge" } else { "delete" }).clicked() {
+                    self.resolve_current_group();
+                }
+            });
+        });
+        modal
+    }
+
+    fn resolve_current_group(&mut self) {
+        let loaded_candidates = match self.candidates.as_ref().and_then(|promise| promise.ready()) {
+            Some(Ok(loaded_candidates)) => loaded_candidates,
+            _ => return,
+        };
+        let kept_hash = match loaded_candidates
+            .get(self.kept_index)
+            .and_then(|loaded_candidate| loaded_candidate.candidate.media_info.details.id.as_media_entry_id())
+        {
+            Some(kept_hash) => kept_hash.clone(),
+            None => return,
+        };
+        let deleted_hashes = self.get_ticked_hashes();
+        if deleted_hashes.is_empty() {
+            return;
+        }
+        SharedState::append_to_update_list(
+            &self.shared_state.deleted_entries,
+            deleted_hashes.iter().map(|hash| EntryId::MediaEntry(hash.clone())).collect(),
+        );
+        SharedState::append_to_update_list(&self.shared_state.updated_entries, vec![EntryId::MediaEntry(kept_hash.clone())]);
+        let merge = self.merge_into_kept;
+        let resolved_hashes = deleted_hashes.clone();
+        self.resolving = Some((
+            resolved_hashes,
+            Promise::spawn_thread("resolve_duplicate_group", move || {
+                data::resolve_duplicate_group(&kept_hash, &deleted_hashes, merge)
+            }),
+        ));
+    }
+
+    fn render_options(&mut self, ui: &mut Ui) {
+        let mut selected_group = None;
+        ui.with_layout(Layout::top_down_justified(Align::Center), |ui| {
+            ui.label("duplicates");
+            let is_searching = self.duplicate_groups.as_ref().map(|promise| promise.ready().is_none()).unwrap_or(false);
+            if ui
+                .add_enabled(!is_searching, egui::Button::new(icon!("find duplicates", SEARCH_ICON)))
+                .on_hover_text("group media whose perceptual hashes are within the near duplicate distance")
+                .clicked()
+            {
+                self.find_duplicates();
+            }
+            if ui
+                .button("not duplicates")
+                .on_hover_text("pairs marked as not duplicates, which finding duplicates leaves apart")
+                .clicked()
+            {
+                self.is_dismissed_window_open = !self.is_dismissed_window_open;
+                if self.is_dismissed_window_open {
+                    self.load_dismissed_duplicates();
+                }
+            }
+            ui::space(ui);
+            match self.duplicate_groups.as_ref().and_then(|promise| promise.ready()) {
+                None if is_searching => {
+                    ui.spinner();
+                }
+                None => (),
+                Some(Err(e)) => {
+                    ui.label(format!("failed to find duplicates: {e}"));
+                }
+                Some(Ok(duplicate_groups)) if duplicate_groups.is_empty() => {
+                    ui.label("no duplicates found");
+                }
+                Some(Ok(duplicate_groups)) => {
+                    let group_count = duplicate_groups.len();
+                    ui.label(format!("group {} of {group_count}", self.current_group_index + 1));
+                    let current_group_index = self.current_group_index;
+                    ui.columns(2, |columns| {
+                        if columns[0].add_enabled(current_group_index > 0, egui::Button::new("previous")).clicked() {
+                            selected_group = Some(current_group_index - 1);
+                        }
+                        if columns[1]
+                            .add_enabled(current_group_index + 1 < group_count, egui::Button::new("next"))
+                            .clicked()
+                        {
+                            selected_group = Some(current_group_index + 1);
+                        }
+                    });
+                }
+            }
+        });
+        if let Some(group_index) = selected_group {
+            self.select_group(group_index);
+        }
+    }
+
+    fn render_group(&mut self, ui: &mut Ui, ctx: &Context, resolve_modal: &Modal) {
+        let loaded_candidates = match self.candidates.as_mut().and_then(|promise| promise.ready_mut()) {
+            Some(Ok(loaded_candidates)) => loaded_candidates,
+            Some(Err(e)) => {
+                ui.label(format!("failed to load duplicates: {e}"));
+                return;
+            }
+            None => {
+                if self.candidates.is_some() {
+                    ui.spinner();
+                }
+                return;
+            }
+        };
+        let thumbnail_size = CANDIDATE_COLUMN_WIDTH;
+        let is_resolving = self.resolving.is_some() || self.dismissing.is_some();
+        let mut dismiss = false;
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.merge_into_kept, "merge into kept")
+                    .on_hover_text("the kept media takes over the tags, sources, score, bookmark and pools of the others");
+                let kept_index = self.kept_index;
+                let ticked_count = loaded_candidates
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, loaded_candidate)| loaded_candidate.is_ticked && *index != kept_index)
+                    .count();
+                if ui
+                    .add_enabled(
+                        !is_resolving && ticked_count > 0,
+                        ui::caution_button(icon!(format!("keep selected, delete {ticked_count} ticked"), DELETE_ICON)),
+                    )
+                    .clicked()
+                {
+                    resolve_modal.open();
+                }
+                if ui
+                    .add_enabled(!is_resolving, egui::Button::new("not duplicates"))
+                    .on_hover_text("remember these aren't duplicates, so they aren't grouped again")
+                    .clicked()
+                {
+                    dismiss = true;
+                }
+            });
+            ui.separator();
+            ScrollArea::both().auto_shrink([false, false]).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    for (index, loaded_candidate) in loaded_candidates.iter_mut().enumerate() {
+                        let candidate = &loaded_candidate.candidate;
+                        let details = &candidate.media_info.details;
+                        ui.allocate_ui(egui::vec2(CANDIDATE_COLUMN_WIDTH, ui.available_height()), |ui| {
+                            ui.vertical(|ui| {
+                                let mut options = ui::RenderLoadingImageOptions::default();
+                                options.desired_image_size = [thumbnail_size, thumbnail_size];
+                                options.is_button = true;
+                                options.is_button_selected = Some(self.kept_index == index);
+                                options.hover_text = Some(details.id.to_string().into());
+                                if let Some(response) = ui::render_loading_preview(ui, ctx, loaded_candidate.thumbnail.as_mut(), &options) {
+                                    if response.clicked() {
+                                        self.kept_index = index;
+                                    }
+                                }
+                                ui.horizontal(|ui| {
+                                    ui.radio_value(&mut self.kept_index, index, "keep");
+                                    ui.add_enabled(self.kept_index != index, egui::Checkbox::new(&mut loaded_candidate.is_ticked, "delete"))
+                                        .on_hover_text("delete this media when resolving, merging it first if merge into kept is on");
+                                });
+                                egui::Grid::new(format!("duplicate_candidate_{index}")).num_columns(2).show(ui, |ui| {
+                                    ui.label("resolution");
+                                    ui.label(
+                                        candidate
+                                            .dimensions
+                                            .map(|(width, height)| format!("{width}x{height}"))
+                                            .unwrap_or(String::from("?")),
+                                    );
+                                    ui.end_row();
+                                    ui.label("size");
+                                    ui.label(ui::readable_byte_size(details.size, 2, ui::NumericBase::Two));
+                                    ui.end_row();
+                                    ui.label("type");
+                                    ui.label(&candidate.media_info.mime);
+                                    ui.end_row();
+                                    ui.label("score");
+                                    ui.label(details.score.to_string());
+                                    ui.end_row();
+                                    ui.label("bookmarked");
+                                    ui.label(if details.is_bookmarked { "yes" } else { "no" });
+                                    ui.end_row();
+                                    ui.label("pools");
+                                    let pools = candidate
+                                        .media_info
+                                        .links
+                                        .iter()
+                                        .map(|link_id| ui::icon_text(link_id, ui::constants::LINK_ICON))
+                                        .collect::<Vec<_>>();
+                                    ui.label(if pools.is_empty() { String::from("none") } else { pools.join(" ") });
+                                    ui.end_row();
+                                });
+                                ui.label(format!("{} tags", details.tags.len()));
+                                ui.horizontal_wrapped(|ui| {
+                                    for tag in &details.tags {
+                                        ui.label(tag.to_rich_text(&self.shared_state));
+                                    }
+                                });
+                            });
+                        });
+                        ui.separator();
+                    }
+                });
+            });
+        });
+        if dismiss {
+            self.dismiss_current_group();
+        }
+    }
+}
+
+impl UserInterface for DuplicatesUI {
+    fn ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        self.process_state();
+        self.render_dismissed_window(ctx);
+        let resolve_modal = self.render_resolve_modal(ctx);
+        StripBuilder::new(ui)
+            .size(Size::exact(0.))
+            .size(Size::exact(ui::constants::OPTIONS_COLUMN_WIDTH))
+            .size(Size::exact(ui::constants::SPACER_SIZE))
+            .size(Size::remainder())
+            .horizontal(|mut strip| {
+                strip.empty();
+                strip.cell(|ui| {
+                    self.render_options(ui);
+                });
+                strip.cell(|ui| {
+                    ui.with_layout(Layout::left_to_right(Align::Center).with_cross_justify(true), |ui| {
+                        ui.separator();
+                    });
+                });
+                strip.cell(|ui| {
+                    self.render_group(ui, ctx, &resolve_modal);
+                });
+            });
+    }
+}
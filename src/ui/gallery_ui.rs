@@ -40,6 +40,7 @@ use super::super::data;
 use super::super::ui;
 use super::super::Config;
 use super::icon;
+use super::import_ui::ClipboardImportModal;
 use super::preview_ui::MediaPreview;
 use super::preview_ui::PreviewStatus;
 use super::preview_ui::PreviewUI;
@@ -101,6 +102,7 @@ pub struct GalleryUI {
     last_hovered: Vec<HoverInfo>,
     actual_thumbnail_size: Option<f32>,
     selected_rects: Vec<Rect>,
+    clipboard_import_modal: ClipboardImportModal,
 }
 
 impl GalleryUI {
@@ -118,6 +120,7 @@ impl GalleryUI {
         Self {
             preview_windows: vec![],
             selected_rects: vec![],
+            clipboard_import_modal: ClipboardImportModal::new(shared_state, "gallery_clipboard_import"),
             refilter_flag: Arc::new(AtomicBool::new(false)),
             new_gallery_entries: Arc::new(Mutex::new(vec![])),
             is_selection_mode: false,
@@ -153,6 +156,7 @@ pub struct EntrySearch {
     pub is_valid: bool,
     pub limit: Option<i64>,
     pub id: Option<String>,
+    pub import_session_id: Option<i64>,
 }
 
 impl Default for EntrySearch {
@@ -171,6 +175,7 @@ impl Default for EntrySearch {
             is_valid: true,
             limit: None,
             id: None,
+            import_session_id: None,
         }
     }
 }
@@ -182,6 +187,7 @@ const INDEPENDANT_QUANTIFIER: &str = "independant";
 const LIMIT_QUANTIFIER: &str = "limit";
 const BOOKMARKED_QUANTIFIER: &str = "bookmarked";
 const ID_QUANTIFIER: &str = "id";
+const SESSION_QUANTIFIER: &str = "session";
 const SCORE_QUANTIFIER: &str = "score";
 const SCORE_Q_QUANTIFIER: &str = "score_q";
 
@@ -204,6 +210,7 @@ impl From<String> for EntrySearch {
             static ref TYPE_RE: Regex = regex(&value_re(TYPE_QUANTIFIER));
             static ref BOOKMARKED_RE: Regex = regex(&value_re(BOOKMARKED_QUANTIFIER));
             static ref ID_RE: Regex = regex(&value_re(ID_QUANTIFIER));
+            static ref SESSION_RE: Regex = regex(&value_re(SESSION_QUANTIFIER));
             static ref INDEPENDANT_RE: Regex = regex(&value_re(INDEPENDANT_QUANTIFIER));
             static ref LIMIT_RE: Regex = regex(&value_re(LIMIT_QUANTIFIER));
             static ref SCORE_RE: Regex = regex(&format!(r"{SCORE_QUANTIFIER}(?P<{SCORE_Q_QUANTIFIER}>.+?)(?P<{SCORE_QUANTIFIER}>\d+)"));
@@ -237,6 +244,12 @@ impl From<String> for EntrySearch {
         for cap in ID_RE.captures_iter(&search) {
             entry_search.id = Some(cap[ID_QUANTIFIER].to_string())
         }
+        for cap in SESSION_RE.captures_iter(&search) {
+            match (&cap[SESSION_QUANTIFIER]).parse() {
+                Ok(import_session_id) => entry_search.import_session_id = Some(import_session_id),
+                Err(_) => entry_search.is_valid = false,
+            }
+        }
         for cap in INDEPENDANT_RE.captures_iter(&search) {
             entry_search.is_independant = str_to_bool(&cap[INDEPENDANT_QUANTIFIER])
         }
@@ -272,6 +285,7 @@ impl From<String> for EntrySearch {
         stripped = SCORE_RE.replace_all(&stripped, "").to_string();
         stripped = LIMIT_RE.replace_all(&stripped, "").to_string();
         stripped = ID_RE.replace_all(&stripped, "").to_string();
+        stripped = SESSION_RE.replace_all(&stripped, "").to_string();
 
         entry_search.and_relations.push(Tag::from_tagstrings(&stripped));
         entry_search
@@ -553,6 +567,7 @@ impl GalleryUI {
     fn render_options(&mut self, ui: &mut Ui, ctx: &egui::Context) {
         let link_modal = self.render_make_link_modal(ctx);
         let delete_selected_modal = self.render_delete_selected_modal(ctx);
+        let clipboard_import_modal = self.clipboard_import_modal.render(ctx);
         ui.with_layout(Layout::top_down_justified(Align::Center), |ui| {
             ui.label("gallery");
             ui.add_enabled_ui(!self.is_loading_gallery_entries(), |ui| {
@@ -582,6 +597,9 @@ impl GalleryUI {
             if ui.button(icon!("shuffle", SHUFFLE_ICON)).clicked() {
                 self.shuffle_entries();
             }
+            if ui.button(icon!("import clipboard", IMPORT_ICON)).clicked() {
+                clipboard_import_modal.open();
+            }
             ui.add_space(ui::constants::SPACER_SIZE);
             if ui.button("select all").clicked() {
                 util::opt_vec_applyeach_refcell(&mut self.filtered_gallery_entries, |gallery_entry| gallery_entry.is_selected = true)
@@ -1060,6 +1078,11 @@ impl GalleryUI {
         gallery_entry.borrow().updated_entry_info.is_none() || gallery_entry.borrow().is_refreshing()
     }
 
+    pub fn set_search(&mut self, search: String) {
+        self.search_string = search;
+        self.filter_entries();
+    }
+
     fn filter_entries(&mut self) {
         puffin::profile_scope!("filter_gallery_entries");
 
@@ -1,9 +1,18 @@
 use anyhow::anyhow;
 use anyhow::Error;
+use chrono::{TimeZone, Utc};
+use data::DuplicateAction;
+use data::EntryId;
+use data::ImportationMetadata;
+use data::ImportReportRow;
+use data::ImportSession;
+use data::ImportSessionFile;
+use data::ImportSessionRecord;
 use data::ImportationStatus;
 use egui::Align2;
 use egui::Color32;
 use egui::Context;
+use egui::Key;
 
 
 
@@ -17,11 +26,14 @@ use egui_extras::StripBuilder;
 use egui_modal::Icon;
 use egui_modal::Modal;
 use tempfile::tempdir;
-use zip::ZipArchive;
 
 use crate::app::SharedState;
+use crate::formats;
 use crate::import;
+use crate::tags::Tag;
 use crate::ui::widgets;
+use crate::ui::widgets::autocomplete;
+use crate::ui::widgets::star_rating::star_rating;
 use crate::util::BatchPollBuffer;
 use crate::util::PollBuffer;
 
@@ -30,23 +42,27 @@ use crate::ui;
 use crate::Config;
 use super::icon;
 use crate::import::scan_directory;
+use crate::import::ScanProgress;
+use crate::import::ScanResult;
 use crate::import::ImportationEntry;
+use crate::import::CompiledImportRule;
+use crate::import::NearDuplicate;
+use crate::import::NearDuplicateAction;
 use anyhow::Result;
 use eframe::egui::{self, Button, Direction, ScrollArea, Ui};
 use eframe::emath::{Align, Vec2};
 use poll_promise::Promise;
 use rfd::FileDialog;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use std::fs;
-use std::fs::File;
-use std::io;
 use std::path::Path;
 use std::path::PathBuf;
 use std::rc::Rc;
 
 use parking_lot::Mutex;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::thread;
 
@@ -67,10 +83,42 @@ pub struct ImporterUI {
     scroll_to: Option<PathBuf>,
     is_import_status_window_open: bool,
     is_filters_window_open: bool,
+    is_rules_window_open: bool,
+    is_pool_order_window_open: bool,
+    rules_dry_run: Option<Vec<(String, Vec<String>)>>,
+    assign_tagstrings: String,
+    assign_score: Option<i64>,
+    assign_bookmark: Option<bool>,
+    duplicate_action: DuplicateAction,
+    near_duplicate_check: Option<Promise<Result<Vec<(PathBuf, NearDuplicate)>>>>,
+    is_near_duplicates_window_open: bool,
+    clipboard_import_modal: ClipboardImportModal,
+    scan: Option<PendingScan>,
+    scan_errors: Vec<(PathBuf, String)>,
+    is_scan_errors_window_open: bool,
+    import_session_id: Option<i64>,
+    // files whose final status has already been saved to the current import session
+    recorded_session_paths: HashSet<PathBuf>,
+    // the one save of the import session in flight, the next waits for it so saves land in order
+    session_save: Option<Promise<Result<()>>>,
+    unfinished_import_session: Option<ImportSession>,
+    checked_unfinished_import_session: bool,
+    is_sessions_window_open: bool,
+    import_session_records: Option<Promise<Result<Vec<ImportSessionRecord>>>>,
+    import_report: Option<(i64, Promise<Result<Vec<ImportReportRow>>>)>,
+    is_report_window_open: bool,
+    reverting_session: Option<ImportSessionRecord>,
     pending_archive_extracts: Option<Vec<ArchiveExtractionProgress>>, // TODO: doesnt need to be an option
     waiting_for_extracts: bool,
 }
 
+struct PendingScan {
+    progress: Arc<ScanProgress>,
+    promise: Promise<Result<ScanResult>>,
+    // the session to restore from the scan, and whether to requeue its unfinished files
+    restoring_session: Option<(ImportSession, bool)>,
+}
+
 struct ArchiveExtractionProgress {
     current_progress: Arc<Mutex<f32>>,
     import_entry_path: PathBuf,
@@ -110,28 +158,55 @@ impl ImporterUI {
             organize_directory: false,
             is_import_status_window_open: false,
             is_filters_window_open: false,
+            is_rules_window_open: false,
+            is_pool_order_window_open: false,
+            rules_dry_run: None,
+            assign_tagstrings: String::new(),
+            assign_score: None,
+            assign_bookmark: None,
+            duplicate_action: Config::global().import.duplicate_action,
+            near_duplicate_check: None,
+            is_near_duplicates_window_open: false,
+            clipboard_import_modal: ClipboardImportModal::new(shared_state, "importer_clipboard_import"),
+            import_session_id: None,
+            recorded_session_paths: HashSet::new(),
+            session_save: None,
+            unfinished_import_session: None,
+            checked_unfinished_import_session: false,
+            is_sessions_window_open: false,
+            import_session_records: None,
+            import_report: None,
+            is_report_window_open: false,
+            scan: None,
+            scan_errors: vec![],
+            is_scan_errors_window_open: false,
+            reverting_session: None,
             hide_errored_entries: true,
             batch_import_status: None,
             waiting_for_extracts: false,
             importation_entries: None,
             alternate_scan_dir: None,
-            scan_extension_filter: Self::build_extension_filter_hashmap(vec![
-                ("image", vec!["png", "jpg", "webp"]),
-                ("movie", vec!["gif", "mp4", "webm"]),
-                ("archive", vec!["zip", "rar"]),
-            ]),
+            scan_extension_filter: Self::build_extension_filter_hashmap(
+                Config::global()
+                    .import
+                    .extension_groups
+                    .iter()
+                    .map(|extension_group| (extension_group.name.clone(), extension_group.extension_list()))
+                    .collect(),
+            ),
             dir_link_map: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    fn build_extension_filter_hashmap(exts: Vec<(&str, Vec<&str>)>) -> HashMap<String, HashMap<String, bool>> {
+    fn build_extension_filter_hashmap(exts: Vec<(String, Vec<String>)>) -> HashMap<String, HashMap<String, bool>> {
         let mut map = HashMap::new();
         for (ext_group, exts) in exts {
             let mut inner_map = HashMap::new();
-            for ext in exts {
-                inner_map.insert(ext.to_string(), true);
+            // only extensions something can be done with are offered
+            for ext in exts.into_iter().filter(|ext| formats::is_supported_extension(ext)) {
+                inner_map.insert(ext, true);
             }
-            map.insert(ext_group.to_string(), inner_map);
+            map.entry(ext_group).or_insert_with(HashMap::new).extend(inner_map);
         }
         return map;
     }
@@ -339,10 +414,21 @@ impl ImporterUI {
     }
 
     fn render_options(&mut self, ui: &mut Ui, ctx: &Context) {
+        let clipboard_import_modal = self.clipboard_import_modal.render(ctx);
+        // ctrl+v outside of a text field imports from the clipboard
+        if !ctx.wants_keyboard_input() && ctx.input(|i| i.modifiers.command && i.key_pressed(Key::V)) {
+            clipboard_import_modal.open();
+        }
         ui.vertical_centered_justified(|ui| {
             ui.label("options");
             ScrollArea::vertical().id_source("options").auto_shrink([false, false]).show(ui, |ui| {
-                if ui
+                if let Some(scan) = self.scan.as_ref() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!("found {} files", scan.progress.found_count.load(Ordering::Relaxed)));
+                    });
+                    ui.ctx().request_repaint();
+                } else if ui
                     .button(format!(
                         "{} {}",
                         ui::constants::SEARCH_ICON,
@@ -350,40 +436,47 @@ impl ImporterUI {
                     ))
                     .clicked()
                 {
-                    match scan_directory(self.get_scan_dir(), 0, None, &self.generate_extension_filter_vec()) {
-                        Ok(media_entries) => {
-                            let warning_amount = 15000;
-                            let amount_entries = media_entries.len();
-                            ui::toast_info_lock(&self.shared_state.toasts, format!("found {amount_entries} entries"));
-                            if amount_entries > warning_amount {
-                                ui::toasts_with_cb(
-                                    &self.shared_state.toasts,
-                                    |toasts| {
-                                        toasts.error(format!(
-                                            "more than {warning_amount} entries: performance may be limited. \
-                                        disabling previews or splitting this directory into multiple \
-                                        directories may help."
-                                        ))
-                                    },
-                                    |toast| toast.set_closable(true).set_duration(None),
-                                );
-                            }
-                            self.importation_entries = Some(media_entries.into_iter().map(|i| Rc::new(RefCell::new(i))).collect());
-                        }
-                        Err(e) => {
-                            ui::toast_error_lock(&self.shared_state.toasts, format!("failed to scan directory: {e}"));
-                        }
-                    }
+                    self.start_scan(self.get_scan_dir(), None);
+                }
+                if !self.scan_errors.is_empty() && ui.button(format!("scan errors ({})", self.scan_errors.len())).clicked() {
+                    self.is_scan_errors_window_open = !self.is_scan_errors_window_open;
                 }
                 ui.add_space(ui::constants::SPACER_SIZE);
                 if ui.button("filters").clicked() {
                     self.is_filters_window_open = !self.is_filters_window_open
                 }
+                if ui.button("rules").clicked() {
+                    self.is_rules_window_open = !self.is_rules_window_open
+                }
+                if ui.button("pool order").clicked() {
+                    self.is_pool_order_window_open = !self.is_pool_order_window_open
+                }
+                if ui.button("near duplicates").clicked() {
+                    self.is_near_duplicates_window_open = !self.is_near_duplicates_window_open
+                }
+                if ui.button("sessions").clicked() {
+                    self.is_sessions_window_open = !self.is_sessions_window_open;
+                    if self.is_sessions_window_open {
+                        self.load_import_session_records();
+                    }
+                }
+                if ui.button("import clipboard").clicked() {
+                    clipboard_import_modal.open();
+                }
 
                 ui.add_space(ui::constants::SPACER_SIZE);
                 ui.group(|ui| {
                     ui.checkbox(&mut self.skip_thumbnails, "disable previews");
-                    ui.checkbox(&mut self.organize_directory, "organize directory");
+                    ui.checkbox(&mut self.organize_directory, "organize directory")
+                        .on_hover_text("move or trash files once they're imported, as set in the config");
+                });
+
+                ui.add_space(ui::constants::SPACER_SIZE);
+                ui.group(|ui| {
+                    ui.label("on duplicates");
+                    for duplicate_action in DuplicateAction::ALL {
+                        ui.selectable_value(&mut self.duplicate_action, duplicate_action, duplicate_action.to_string());
+                    }
                 });
 
                 ui.add_space(ui::constants::SPACER_SIZE);
@@ -414,39 +507,6 @@ impl ImporterUI {
                     .add_enabled(self.is_any_entry_selected(), ui::suggested_button(icon!("import", IMPORT_ICON)))
                     .clicked()
                 {
-                    if self.organize_directory {
-                        let import_success_path = self.get_import_success_path();
-                        let import_fail_path = self.get_import_fail_path();
-                        let import_duplicate_path = self.get_import_duplicate_path();
-
-                        let all_dir_creation_res = vec![
-                            if import_success_path.exists() {
-                                Ok(())
-                            } else {
-                                fs::create_dir(import_success_path)
-                            },
-                            if import_fail_path.exists() {
-                                Ok(())
-                            } else {
-                                fs::create_dir(import_fail_path)
-                            },
-                            if import_duplicate_path.exists() {
-                                Ok(())
-                            } else {
-                                fs::create_dir(import_duplicate_path)
-                            },
-                        ];
-
-                        if all_dir_creation_res.iter().any(|res| res.is_err()) {
-                            for res in all_dir_creation_res {
-                                if let Err(e) = res {
-                                    ui::toast_error_lock(&self.shared_state.toasts, format!("failed to create dir: {e}"))
-                                }
-                            }
-                            return;
-                        }
-                    }
-
                     let selected_media_entries = self.get_selected_media_entries();
                     let mut selected_archives_exist = false;
                     ui::toast_info_lock(
@@ -454,13 +514,32 @@ impl ImporterUI {
                         format!("marked {} media for importing", selected_media_entries.len()),
                     );
 
+                    let mut skipped_near_duplicates = 0;
                     for import_entry in self.get_selected_media_entries() {
+                        let is_skipped_near_duplicate = import_entry
+                            .borrow()
+                            .near_duplicate
+                            .as_ref()
+                            .map(|near_duplicate| near_duplicate.action == NearDuplicateAction::Skip)
+                            .unwrap_or(false);
+                        if is_skipped_near_duplicate {
+                            skipped_near_duplicates += 1;
+                            import_entry.borrow_mut().is_selected = false;
+                            continue;
+                        }
                         if import_entry.borrow().is_archive {
                             selected_archives_exist = true;
                         }
                         import_entry.borrow_mut().importation_status = Some(Promise::from_ready(ImportationStatus::Pending));
                         import_entry.borrow_mut().is_selected = false;
                     }
+                    self.record_import_session();
+                    if skipped_near_duplicates > 0 {
+                        ui::toast_warning_lock(
+                            &self.shared_state.toasts,
+                            format!("skipped {skipped_near_duplicates} possible duplicates"),
+                        );
+                    }
                     if selected_archives_exist {
                         self.waiting_for_extracts = true;
                         prompt.open();
@@ -473,14 +552,459 @@ impl ImporterUI {
         });
     }
 
-    fn get_import_success_path(&self) -> PathBuf {
-        self.get_scan_dir().join(import::SUCCESS_IMPORT_DIR)
+    fn session_file(&self, media_entry: &ImportationEntry) -> ImportSessionFile {
+        ImportSessionFile {
+            path: media_entry.source_path(),
+            is_selected: media_entry.is_selected,
+            status: media_entry.importation_status.as_ref().and_then(|promise| promise.ready()).cloned(),
+        }
     }
-    fn get_import_fail_path(&self) -> PathBuf {
-        self.get_scan_dir().join(import::FAILED_IMPORT_DIR)
+
+    // saves the scanned files to the import session, so the import can be resumed if htool closes before it finishes
+    fn record_import_session(&mut self) {
+        // files extracted from archives only exist in a temporary directory, so they can't be resumed
+        let files = self
+            .filter_media_entries(|media_entry| media_entry.borrow().source_archive.is_none())
+            .iter()
+            .map(|media_entry| self.session_file(&media_entry.borrow()))
+            .collect::<Vec<_>>();
+        let session_res = match self.import_session_id {
+            Some(session_id) => data::update_import_session(session_id, &files, &self.dir_link_map.lock()).map(|_| session_id),
+            None => data::create_import_session(&self.get_scan_dir(), &files),
+        };
+        match session_res {
+            Ok(session_id) => {
+                self.import_session_id = Some(session_id);
+                for file in files {
+                    if !matches!(file.status, None | Some(ImportationStatus::Pending)) {
+                        self.recorded_session_paths.insert(file.path);
+                    }
+                }
+            }
+            Err(e) => ui::toast_warning_lock(
+                &self.shared_state.toasts,
+                format!("failed to save import session, it won't be resumable: {e}"),
+            ),
+        }
+    }
+
+    fn process_import_session(&mut self) {
+        if !self.checked_unfinished_import_session && SharedState::read_update_flag(&self.shared_state.database_unlocked) {
+            self.checked_unfinished_import_session = true;
+            match data::get_unfinished_import_session() {
+                Ok(import_session) => self.unfinished_import_session = import_session,
+                Err(e) => ui::toast_error_lock(&self.shared_state.toasts, format!("failed to load unfinished import: {e}")),
+            }
+        }
+
+        if let Some(session_save) = self.session_save.take() {
+            match session_save.try_take() {
+                Ok(Ok(())) => (),
+                Ok(Err(e)) => ui::toast_error_lock(&self.shared_state.toasts, format!("failed to save import session: {e}")),
+                Err(session_save) => {
+                    self.session_save = Some(session_save);
+                    return;
+                }
+            }
+        }
+
+        let session_id = match self.import_session_id {
+            Some(session_id) => session_id,
+            None => return,
+        };
+        let mut is_session_finished = !self.waiting_for_extracts && self.pending_archive_extracts.is_none();
+        let mut finished_files = vec![];
+        // extracted files are saved once they have an outcome, under their path inside the archive, so they're in the report
+        for media_entry in self.filter_media_entries(|_media_entry| true) {
+            let media_entry = media_entry.borrow();
+            if media_entry.is_importing() {
+                is_session_finished = false;
+            } else if media_entry.importation_status.is_some() && self.recorded_session_paths.insert(media_entry.source_path()) {
+                finished_files.push(self.session_file(&media_entry));
+            }
+        }
+        if finished_files.is_empty() && !is_session_finished {
+            return;
+        }
+
+        let dir_link_map = self.dir_link_map.lock().clone();
+        self.session_save = Some(Promise::spawn_thread("save_import_session", move || {
+            data::update_import_session(session_id, &finished_files, &dir_link_map)?;
+            if is_session_finished {
+                data::finish_import_session(session_id)?;
+            }
+            Ok(())
+        }));
+        if is_session_finished {
+            self.import_session_id = None;
+            self.recorded_session_paths.clear();
+        }
     }
-    fn get_import_duplicate_path(&self) -> PathBuf {
-        self.get_scan_dir().join(import::DUPLICATE_IMPORT_DIR)
+
+    // scans on another thread so the window stays responsive, see process_scan
+    fn start_scan(&mut self, scan_dir: PathBuf, restoring_session: Option<(ImportSession, bool)>) {
+        let progress = Arc::new(ScanProgress::default());
+        let extension_filter = self.generate_extension_filter_vec();
+        let promise = Promise::spawn_thread("scan_directory", {
+            let progress = Arc::clone(&progress);
+            move || scan_directory(scan_dir, &extension_filter, &progress, &HashSet::new())
+        });
+        self.scan = Some(PendingScan {
+            progress,
+            promise,
+            restoring_session,
+        });
+    }
+
+    fn process_scan(&mut self) {
+        if !self.scan.as_ref().map(|scan| scan.promise.ready().is_some()).unwrap_or(false) {
+            return;
+        }
+        let scan = match self.scan.take() {
+            Some(scan) => scan,
+            None => return,
+        };
+        let scan_result = match scan.promise.try_take() {
+            Ok(Ok(scan_result)) => scan_result,
+            Ok(Err(e)) => {
+                ui::toast_error_lock(&self.shared_state.toasts, format!("failed to scan directory: {e}"));
+                return;
+            }
+            Err(_) => return,
+        };
+        self.scan_errors = scan_result.errors;
+        if !self.scan_errors.is_empty() {
+            ui::toast_warning_lock(
+                &self.shared_state.toasts,
+                format!("{} files or folders couldn't be scanned", self.scan_errors.len()),
+            );
+        }
+        match scan.restoring_session {
+            Some((import_session, requeue)) => self.restore_import_session(import_session, requeue, scan_result.import_entries),
+            None => self.load_scanned_entries(scan_result.import_entries),
+        }
+    }
+
+    fn load_scanned_entries(&mut self, media_entries: Vec<ImportationEntry>) {
+        let warning_amount = 15000;
+        let amount_entries = media_entries.len();
+        ui::toast_info_lock(&self.shared_state.toasts, format!("found {amount_entries} entries"));
+        if amount_entries > warning_amount {
+            ui::toasts_with_cb(
+                &self.shared_state.toasts,
+                |toasts| {
+                    toasts.error(format!(
+                        "more than {warning_amount} entries: performance may be limited. \
+                    disabling previews or splitting this directory into multiple \
+                    directories may help."
+                    ))
+                },
+                |toast| toast.set_closable(true).set_duration(None),
+            );
+        }
+        self.importation_entries = Some(media_entries.into_iter().map(|i| Rc::new(RefCell::new(i))).collect());
+        if Config::global().import.near_duplicate_check {
+            self.start_near_duplicate_check();
+        }
+    }
+
+    fn render_scan_errors_window(&mut self, ctx: &Context) {
+        let scan_errors = &self.scan_errors;
+        egui::Window::new("scan errors")
+            .open(&mut self.is_scan_errors_window_open)
+            .show(ctx, |ui| {
+                ScrollArea::both().auto_shrink([false, false]).show(ui, |ui| {
+                    egui::Grid::new("scan_errors").striped(true).show(ui, |ui| {
+                        for (path, error) in scan_errors {
+                            ui.label(path.display().to_string());
+                            ui.label(error);
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+    }
+
+    // puts back each file's selection and status from a rescan of the session's folder.
+    // when requeueing, files that never finished are imported again
+    fn restore_import_session(&mut self, import_session: ImportSession, requeue: bool, mut media_entries: Vec<ImportationEntry>) {
+        let mut files = import_session
+            .files
+            .into_iter()
+            .map(|file| (file.path.clone(), file))
+            .collect::<HashMap<_, _>>();
+        self.recorded_session_paths.clear();
+        for media_entry in media_entries.iter_mut() {
+            let path = media_entry.dir_entry.path();
+            if let Some(file) = files.remove(&path) {
+                media_entry.is_selected = file.is_selected;
+                match file.status {
+                    Some(ImportationStatus::Pending) if requeue => {
+                        media_entry.importation_status = Some(Promise::from_ready(ImportationStatus::Pending));
+                    }
+                    Some(ImportationStatus::Pending) => media_entry.is_selected = true,
+                    Some(status) => {
+                        media_entry.importation_status = Some(Promise::from_ready(status));
+                        self.recorded_session_paths.insert(path);
+                    }
+                    None => (),
+                }
+            }
+        }
+        // files with an outcome were imported already (or extracted from an archive), they aren't needed
+        files.retain(|_path, file| matches!(file.status, None | Some(ImportationStatus::Pending)));
+        if !files.is_empty() {
+            ui::toast_warning_lock(
+                &self.shared_state.toasts,
+                format!(
+                    "{} files from the import are no longer in {}",
+                    files.len(),
+                    import_session.scan_dir.display()
+                ),
+            );
+        }
+
+        self.alternate_scan_dir = Some(import_session.scan_dir);
+        *self.dir_link_map.lock() = import_session.dir_link_map;
+        self.importation_entries = Some(media_entries.into_iter().map(|i| Rc::new(RefCell::new(i))).collect());
+        self.import_session_id = Some(import_session.session_id);
+        self.is_import_status_window_open = true;
+    }
+
+    fn render_unfinished_session_window(&mut self, ctx: &Context) {
+        let mut restore = None;
+        let mut discard = false;
+        if let Some(import_session) = self.unfinished_import_session.as_ref() {
+            egui::Window::new("unfinished import")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    let date_started = Utc
+                        .timestamp_opt(import_session.date_started, 0)
+                        .single()
+                        .map(|datetime| datetime.format("%B %e, %Y @%l:%M%P").to_string())
+                        .unwrap_or_default();
+                    ui.label(format!(
+                        "the import of {} started {date_started} didn't finish",
+                        import_session.scan_dir.display()
+                    ));
+                    ui.separator();
+                    let count = |status: ImportationStatus| import_session.files.iter().filter(|file| file.status.as_ref() == Some(&status)).count();
+                    ui.label(format!("{} imported", count(ImportationStatus::Success)));
+                    let duplicate_count = import_session
+                        .files
+                        .iter()
+                        .filter(|file| matches!(file.status, Some(ImportationStatus::Duplicate(_))))
+                        .count();
+                    ui.label(format!("{duplicate_count} duplicates"));
+                    ui.label(format!("{} previously deleted", count(ImportationStatus::PreviouslyDeleted)));
+                    ui.label(format!("{} failed", count(ImportationStatus::Fail(anyhow!("")))));
+                    ui.label(format!("{} not imported yet", count(ImportationStatus::Pending)));
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.button("resume").on_hover_text("import the remaining files").clicked() {
+                            restore = Some(true);
+                        }
+                        if ui
+                            .button("review")
+                            .on_hover_text("show what completed, and select the remaining files")
+                            .clicked()
+                        {
+                            restore = Some(false);
+                        }
+                        if ui.add(ui::caution_button("discard")).clicked() {
+                            discard = true;
+                        }
+                    });
+                });
+        }
+        if let Some(requeue) = restore {
+            if let Some(import_session) = self.unfinished_import_session.take() {
+                self.start_scan(import_session.scan_dir.clone(), Some((import_session, requeue)));
+            }
+        }
+        if discard {
+            if let Some(import_session) = self.unfinished_import_session.take() {
+                if let Err(e) = data::finish_import_session(import_session.session_id) {
+                    ui::toast_error_lock(&self.shared_state.toasts, format!("failed to discard import session: {e}"));
+                }
+            }
+        }
+    }
+
+    fn load_import_session_records(&mut self) {
+        self.import_session_records = Some(Promise::spawn_thread("load_import_sessions", || data::get_import_session_records()));
+    }
+
+    fn render_sessions_window(&mut self, ctx: &Context) {
+        let revert_modal = self.render_revert_session_modal(ctx);
+        let mut is_open = self.is_sessions_window_open;
+        let mut requested_report = None;
+        egui::Window::new("import sessions").open(&mut is_open).show(ctx, |ui| {
+            match self.import_session_records.as_ref().and_then(|promise| promise.ready()) {
+                None => {
+                    ui.spinner();
+                }
+                Some(Err(e)) => {
+                    ui.label(format!("failed to load import sessions: {e}"));
+                }
+                Some(Ok(records)) if records.is_empty() => {
+                    ui.label("no imports yet");
+                }
+                Some(Ok(records)) => {
+                    ScrollArea::vertical().max_height(400.).show(ui, |ui| {
+                        egui::Grid::new("import_sessions").num_columns(5).striped(true).show(ui, |ui| {
+                            ui.label("started");
+                            ui.label("folder");
+                            ui.label("results");
+                            ui.label("entries");
+                            ui.end_row();
+                            for record in records {
+                                let date_started = Utc
+                                    .timestamp_opt(record.date_started, 0)
+                                    .single()
+                                    .map(|datetime| datetime.format("%B %e, %Y @%l:%M%P").to_string())
+                                    .unwrap_or_default();
+                                ui.label(date_started);
+                                ui.label(record.scan_dir.display().to_string());
+                                ui.label(format!(
+                                    "{} imported, {} duplicates, {} failed{}",
+                                    record.imported_count,
+                                    record.duplicate_count,
+                                    record.failed_count,
+                                    if record.is_finished { "" } else { " (unfinished)" }
+                                ));
+                                ui.label(record.entry_count.to_string());
+                                ui.horizontal(|ui| {
+                                    if ui.button("show").on_hover_text(format!("session={}", record.session_id)).clicked() {
+                                        *self.shared_state.gallery_search_request.borrow_mut() = Some(format!("session={}", record.session_id));
+                                    }
+                                    if ui.button("report").clicked() {
+                                        requested_report = Some(record.session_id);
+                                    }
+                                    let is_current_session = self.import_session_id == Some(record.session_id);
+                                    if ui
+                                        .add_enabled(!is_current_session, ui::caution_button("revert"))
+                                        .on_disabled_hover_text("this import is still running")
+                                        .clicked()
+                                    {
+                                        self.reverting_session = Some(record.clone());
+                                        revert_modal.open();
+                                    }
+                                });
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+            }
+        });
+        self.is_sessions_window_open = is_open;
+        if let Some(session_id) = requested_report {
+            self.load_import_report(session_id);
+        }
+    }
+
+    fn load_import_report(&mut self, session_id: i64) {
+        self.import_report = Some((
+            session_id,
+            Promise::spawn_thread("load_import_report", move || data::get_import_report(session_id)),
+        ));
+        self.is_report_window_open = true;
+    }
+
+    fn render_report_window(&mut self, ctx: &Context) {
+        let (session_id, report) = match self.import_report.as_ref() {
+            Some((session_id, report)) => (*session_id, report),
+            None => return,
+        };
+        egui::Window::new("import report")
+            .open(&mut self.is_report_window_open)
+            .show(ctx, |ui| match report.ready() {
+                None => {
+                    ui.spinner();
+                }
+                Some(Err(e)) => {
+                    ui.label(format!("failed to load import report: {e}"));
+                }
+                Some(Ok(rows)) => {
+                    ui.horizontal(|ui| {
+                        for extension in ["csv", "json"] {
+                            if ui.button(icon!(format!("export {extension}"), EXPORT_ICON)).clicked() {
+                                if let Some(export_path) = FileDialog::new()
+                                    .add_filter(extension, &[extension])
+                                    .set_file_name(&format!("import_report_{session_id}.{extension}"))
+                                    .save_file()
+                                {
+                                    match data::export_import_report(rows, &export_path) {
+                                        Ok(()) => ui::toast_success_lock(&self.shared_state.toasts, "exported import report"),
+                                        Err(e) => ui::toast_error_lock(&self.shared_state.toasts, format!("failed to export import report: {e}")),
+                                    }
+                                }
+                            }
+                        }
+                    });
+                    ui.separator();
+                    ScrollArea::both().max_height(400.).show(ui, |ui| {
+                        egui::Grid::new("import_report").num_columns(5).striped(true).show(ui, |ui| {
+                            ui.label("file");
+                            ui.label("outcome");
+                            ui.label("hash");
+                            ui.label("pool");
+                            ui.label("error");
+                            ui.end_row();
+                            for row in rows {
+                                ui.label(&row.path);
+                                ui.label(&row.outcome);
+                                ui.label(row.hash.as_ref().map(ui::pretty_media_id).unwrap_or_default());
+                                ui.label(row.pool.as_ref().map(ui::pretty_link_id).unwrap_or_default());
+                                ui.label(row.error.as_deref().unwrap_or_default());
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+            });
+    }
+
+    fn render_revert_session_modal(&mut self, ctx: &Context) -> Modal {
+        let modal = ui::modal(ctx, "revert_import_session");
+        modal.show(|ui| {
+            if let Some(record) = self.reverting_session.as_ref() {
+                modal.title(ui, "revert import");
+                modal.frame(ui, |ui| {
+                    modal.body_and_icon(
+                        ui,
+                        format!(
+                            "delete the {} media and pools created by the import of {}? this can't be undone.",
+                            record.entry_count,
+                            record.scan_dir.display()
+                        ),
+                        Icon::Warning,
+                    );
+                });
+                modal.buttons(ui, |ui| {
+                    modal.button(ui, "cancel");
+                    if modal.caution_button(ui, "revert").clicked() {
+                        let session_id = record.session_id;
+                        let toasts = Arc::clone(&self.shared_state.toasts);
+                        let deleted_entries = Arc::clone(&self.shared_state.deleted_entries);
+                        // the records are reloaded once the revert is done
+                        self.import_session_records = Some(Promise::spawn_thread("revert_import_session", move || {
+                            match data::revert_import_session(session_id) {
+                                Ok(entry_ids) => {
+                                    ui::toast_success_lock(&toasts, format!("reverted import, deleted {} entries", entry_ids.len()));
+                                    SharedState::append_to_update_list(&deleted_entries, entry_ids);
+                                }
+                                Err(e) => ui::toast_error_lock(&toasts, format!("failed to revert import: {e}")),
+                            }
+                            data::get_import_session_records()
+                        }));
+                    }
+                });
+            }
+        });
+        modal
     }
 
     fn process_extractions(&mut self) {
@@ -532,27 +1056,9 @@ impl ImporterUI {
                     thread::spawn(move || {
                         let extract = || -> Result<Vec<ImportationEntry>> {
                             let temp_dir = tempdir()?;
-                            let file = File::open(&entry_path)?;
-                            let mut archive = ZipArchive::new(file)?;
-                            for i in 0..archive.len() {
-                                let mut current_progress = current_progress.lock();
-                                *current_progress = i as f32 / archive.len() as f32;
-                                drop(current_progress);
-
-                                let mut next_inner_file = archive.by_index(i)?;
-                                if let Some(next_inner_file_name) = next_inner_file.enclosed_name() {
-                                    let next_inner_file_path = temp_dir.path().join(next_inner_file_name);
-                                    let mut output_file = File::create(&next_inner_file_path)?;
-                                    io::copy(&mut next_inner_file, &mut output_file)?;
-                                }
-                            }
-
-                            let mut import_entries = scan_directory(
-                                temp_dir.path().to_path_buf(),
-                                0,
-                                Some(temp_dir.path().as_os_str().to_string_lossy().to_string()),
-                                &extension_filter,
-                            );
+                            let mut import_entries = import::extract_archive(&entry_path, temp_dir.path(), &extension_filter, &mut |progress| {
+                                *current_progress.lock() = progress
+                            });
 
                             if let Ok(import_entries) = import_entries.as_mut() {
                                 import_entries.iter_mut().for_each(|i| {
@@ -590,6 +1096,14 @@ impl ImporterUI {
         ui.vertical(|files_col| {
             files_col.label("file");
             if let Some(scanned_dirs) = &mut self.importation_entries {
+                let pool_count = scanned_dirs
+                    .iter()
+                    .filter_map(|media_entry| media_entry.borrow().linking_dir.clone())
+                    .collect::<HashSet<_>>()
+                    .len();
+                if pool_count > 0 {
+                    files_col.weak(format!("{pool_count} pools from folders"));
+                }
                 ScrollArea::vertical()
                     .id_source("files_col")
                     .auto_shrink([false; 2])
@@ -618,7 +1132,11 @@ impl ImporterUI {
                                     label = ui::icon_text(label, ui::constants::ERROR_ICON);
                                 } else if media_entry.borrow().match_importation_status(ImportationStatus::Success) {
                                     label = ui::icon_text(label, ui::constants::SUCCESS_ICON);
-                                } else if media_entry.borrow().match_importation_status(ImportationStatus::Duplicate) {
+                                } else if media_entry.borrow().is_duplicate() {
+                                    label = ui::icon_text(label, ui::constants::WARNING_ICON);
+                                } else if media_entry.borrow().match_importation_status(ImportationStatus::PreviouslyDeleted) {
+                                    label = ui::icon_text(label, ui::constants::DELETE_ICON);
+                                } else if media_entry.borrow().mime_mismatch().is_some() {
                                     label = ui::icon_text(label, ui::constants::WARNING_ICON);
                                 }
 
@@ -640,6 +1158,9 @@ impl ImporterUI {
                                 if let Some(status) = media_entry.borrow().get_status_label() {
                                     response = response.on_hover_text(format!("({status})"));
                                 }
+                                if let Some(folder_mapping) = media_entry.borrow().folder_mapping_label() {
+                                    response = response.on_hover_text(folder_mapping);
+                                }
                                 if was_truncated {
                                     response.on_hover_text_at_pointer(&media_entry.borrow().file_label);
                                 }
@@ -650,6 +1171,60 @@ impl ImporterUI {
         });
     }
 
+    fn start_near_duplicate_check(&mut self) {
+        let images = self
+            .get_importable_media_entries()
+            .iter()
+            .map(|media_entry| {
+                let media_entry = media_entry.borrow();
+                let bytes = match media_entry.bytes.as_ref().and_then(|promise| promise.ready()) {
+                    Some(Ok(bytes)) => Some(Arc::clone(bytes)),
+                    _ => None,
+                };
+                (media_entry.dir_entry.path(), bytes)
+            })
+            .collect::<Vec<_>>();
+        let max_distance = Config::global().import.near_duplicate_distance;
+        self.near_duplicate_check = Some(Promise::spawn_thread("near_duplicate_check", move || {
+            import::find_near_duplicates(images, max_distance)
+        }));
+    }
+
+    fn process_near_duplicate_check(&mut self) {
+        if !self
+            .near_duplicate_check
+            .as_ref()
+            .map(|promise| promise.ready().is_some())
+            .unwrap_or(false)
+        {
+            return;
+        }
+        if let Some(Ok(near_duplicates_res)) = self.near_duplicate_check.take().map(|promise| promise.try_take()) {
+            match near_duplicates_res {
+                Ok(near_duplicates) => {
+                    let found_count = near_duplicates.len();
+                    let media_entries = self
+                        .importation_entries
+                        .as_ref()
+                        .map(|media_entries| media_entries.as_slice())
+                        .unwrap_or(&[]);
+                    for (path, near_duplicate) in near_duplicates {
+                        if let Some(media_entry) = media_entries.iter().find(|media_entry| media_entry.borrow().dir_entry.path() == path) {
+                            let mut media_entry = media_entry.borrow_mut();
+                            media_entry.near_duplicate = Some(near_duplicate);
+                            media_entry.is_selected = false;
+                        }
+                    }
+                    if found_count > 0 {
+                        ui::toast_warning_lock(&self.shared_state.toasts, format!("found {found_count} possible duplicates"));
+                        self.is_near_duplicates_window_open = true;
+                    }
+                }
+                Err(e) => ui::toast_error_lock(&self.shared_state.toasts, format!("failed to check for near duplicates: {e}")),
+            }
+        }
+    }
+
     fn process_media(&mut self) {
         puffin::profile_scope!("import_process_media");
 
@@ -657,6 +1232,7 @@ impl ImporterUI {
         self.import_buffer.poll();
 
         if let Some(import_entries) = self.importation_entries.as_ref() {
+            let mut organize_jobs = vec![];
             for import_entry in import_entries {
                 //unload bytes if uneccesary
                 if !import_entry.borrow().keep_bytes_loaded && import_entry.borrow().are_bytes_loaded() {
@@ -678,44 +1254,57 @@ impl ImporterUI {
                 if !self.waiting_for_extracts && import_entry.borrow().match_importation_status(ImportationStatus::Pending) {
                     let _ = self.import_buffer.try_add_entry(&import_entry);
                 }
-                if import_entry.borrow().match_importation_status(ImportationStatus::Duplicate)
+                if import_entry.borrow().is_duplicate()
+                    || import_entry.borrow().match_importation_status(ImportationStatus::PreviouslyDeleted)
                     || import_entry.borrow().match_importation_status(ImportationStatus::Success)
                 {
                     import_entry.borrow_mut().keep_bytes_loaded = false;
                 }
-                if self.organize_directory {
-                    if !import_entry.borrow().attempted_organize {
-                        let entry_path = import_entry.borrow().dir_entry.path();
-                        if Path::exists(&entry_path) {
-                            let entry_file_name = entry_path.file_name().unwrap_or_default();
-                            let new_path = if import_entry.borrow().match_importation_status(ImportationStatus::Duplicate) {
-                                Some(self.get_import_duplicate_path().join(entry_file_name))
-                            } else if import_entry.borrow().match_importation_status(ImportationStatus::Fail(anyhow!(""))) {
-                                Some(self.get_import_fail_path().join(entry_file_name))
-                            } else if import_entry.borrow().match_importation_status(ImportationStatus::Success) {
-                                Some(self.get_import_success_path().join(entry_file_name))
-                            } else {
-                                None
-                            };
-                            if let Some(new_path) = new_path {
-                                if let Err(e) = fs::rename(entry_path, new_path) {
-                                    ui::toast_error_lock(&self.shared_state.toasts, format!("failed to organize entry: {e}"))
-                                }
-                            }
+                if self.organize_directory && !import_entry.borrow().attempted_organize {
+                    let organize_rule = import_entry
+                        .borrow()
+                        .importation_status
+                        .as_ref()
+                        .and_then(|promise| promise.ready())
+                        .and_then(|status| Config::global().import.organize_rule(status).cloned());
+                    // entries extracted from archives have no original left to organize
+                    if let Some(organize_rule) = organize_rule {
+                        if import_entry.borrow().source_archive.is_none() && Path::exists(&import_entry.borrow().dir_entry.path()) {
+                            organize_jobs.push((import_entry.borrow().organize_paths(), organize_rule));
                         }
-
                         import_entry.borrow_mut().attempted_organize = true;
                     }
-                    // if media_entry.borrow().dir_entry.path().starts_with(base)
                 }
             }
+            if !organize_jobs.is_empty() {
+                let scan_dir = self.get_scan_dir();
+                let toasts = Arc::clone(&self.shared_state.toasts);
+                thread::spawn(move || {
+                    for (paths, organize_rule) in organize_jobs {
+                        if let Err(e) = import::organize_file(&paths, &organize_rule, &scan_dir) {
+                            ui::toast_error_lock(&toasts, format!("failed to organize entry: {e}"))
+                        }
+                    }
+                });
+            }
             if self.import_buffer.ready_for_batch_action() {
+                let import_rules = import::compile_import_rules(&Config::global().import.rules);
                 let reg_forms = self
                     .import_buffer
                     .poll_buffer
                     .entries
                     .iter()
-                    .filter_map(|media_entry| media_entry.borrow_mut().generate_reg_form(Arc::clone(&self.dir_link_map)).ok())
+                    .filter_map(|media_entry| {
+                        media_entry
+                            .borrow_mut()
+                            .generate_reg_form(
+                                Arc::clone(&self.dir_link_map),
+                                self.duplicate_action,
+                                self.import_session_id,
+                                &import_rules,
+                            )
+                            .ok()
+                    })
                     .collect::<Vec<_>>();
 
                 self.import_buffer
@@ -729,6 +1318,7 @@ impl ImporterUI {
 
         ui.vertical(|ui| {
             ui.label("preview");
+            self.render_assign_metadata(ui);
 
             if let Some(importation_entries) = self.importation_entries.as_mut() {
                 // iterate through each mediaentry to draw its name on the sidebar, and to load its image
@@ -759,16 +1349,24 @@ impl ImporterUI {
                                     options.hover_text_on_loading_image = Some(format!("{file_label} (loading thumbnail...)",).into());
                                     options.hover_text_on_error_image = Some(Box::new(move |error| format!("{file_label_clone} ({error})").into()));
                                     options.hover_text_on_none_image = Some(format!("{file_label} (waiting to load image...)").into());
+                                    let mut metadata = importation_entry.metadata.clone();
+                                    metadata.merge(importation_entry.assigned_metadata.clone());
+                                    let metadata_label = metadata
+                                        .summary()
+                                        .map(|summary| format!("\n{summary}"))
+                                        .unwrap_or_default();
                                     options.hover_text = Some(if let Some(status_label) = importation_entry.get_status_label() {
-                                        format!("{file_label} ({status_label})").into()
+                                        format!("{file_label} ({status_label}){metadata_label}").into()
                                     } else {
-                                        format!("{file_label}").into()
+                                        format!("{file_label}{metadata_label}").into()
                                     });
                                     options.image_tint = if importation_entry.match_importation_status(data::ImportationStatus::Success) {
                                         Some(ui::constants::IMPORT_IMAGE_SUCCESS_TINT)
                                     } else if importation_entry.match_importation_status(data::ImportationStatus::Fail(anyhow::Error::msg(""))) {
                                         Some(ui::constants::IMPORT_IMAGE_FAIL_TINT)
-                                    } else if importation_entry.match_importation_status(data::ImportationStatus::Duplicate) {
+                                    } else if importation_entry.is_duplicate()
+                                        || importation_entry.match_importation_status(data::ImportationStatus::PreviouslyDeleted)
+                                    {
                                         Some(ui::constants::IMPORT_IMAGE_DUPLICATE_TINT)
                                     } else {
                                         None
@@ -801,6 +1399,69 @@ impl ImporterUI {
         });
     }
 
+    fn render_assign_metadata(&mut self, ui: &mut Ui) {
+        let selected_media_entries = self.get_selected_media_entries();
+        egui::CollapsingHeader::new(format!("assign to {} selected", selected_media_entries.len()))
+            .id_source("assign_metadata")
+            .show(ui, |ui| {
+                egui::Grid::new("assign_metadata_grid").num_columns(2).show(ui, |ui| {
+                    ui.label("tags");
+                    if let Some(options) = self.shared_state.autocomplete_options.borrow().as_ref() {
+                        ui.add(autocomplete::create(&mut self.assign_tagstrings, options, false, true));
+                    } else {
+                        ui.text_edit_singleline(&mut self.assign_tagstrings);
+                    }
+                    ui.end_row();
+
+                    let mut is_setting_score = self.assign_score.is_some();
+                    if ui.checkbox(&mut is_setting_score, "score").changed() {
+                        self.assign_score = if is_setting_score { Some(0) } else { None };
+                    }
+                    if let Some(score) = self.assign_score.as_mut() {
+                        star_rating(ui, score, Config::global().general.entry_max_score);
+                    } else {
+                        ui.weak("unchanged");
+                    }
+                    ui.end_row();
+
+                    let mut is_setting_bookmark = self.assign_bookmark.is_some();
+                    if ui.checkbox(&mut is_setting_bookmark, "bookmark").changed() {
+                        self.assign_bookmark = if is_setting_bookmark { Some(true) } else { None };
+                    }
+                    if let Some(is_bookmarked) = self.assign_bookmark.as_mut() {
+                        ui.checkbox(is_bookmarked, "bookmarked");
+                    } else {
+                        ui.weak("unchanged");
+                    }
+                    ui.end_row();
+                });
+
+                ui.horizontal(|ui| {
+                    let is_any_selected = !selected_media_entries.is_empty();
+                    if ui.add_enabled(is_any_selected, Button::new("apply")).clicked() {
+                        let assigned_metadata = ImportationMetadata {
+                            tags: Tag::from_tagstrings(&self.assign_tagstrings),
+                            score: self.assign_score,
+                            is_bookmarked: self.assign_bookmark,
+                            sources: vec![],
+                        };
+                        for media_entry in &selected_media_entries {
+                            media_entry.borrow_mut().assigned_metadata.merge(assigned_metadata.clone());
+                        }
+                        ui::toast_info_lock(
+                            &self.shared_state.toasts,
+                            format!("assigned to {} selected entries", selected_media_entries.len()),
+                        );
+                    }
+                    if ui.add_enabled(is_any_selected, Button::new("clear")).clicked() {
+                        for media_entry in &selected_media_entries {
+                            media_entry.borrow_mut().assigned_metadata = ImportationMetadata::default();
+                        }
+                    }
+                });
+            });
+    }
+
     fn render_filters_window(&mut self, ctx: &Context) {
         egui::Window::new("filters")
             .open(&mut self.is_filters_window_open)
@@ -826,6 +1487,206 @@ impl ImporterUI {
             });
     }
 
+    // evaluates the configured rules without importing anything, so they can be checked before a batch import
+    fn dry_run_rules(&self) -> Vec<(String, Vec<String>)> {
+        let compiled_rules = Config::global()
+            .import
+            .rules
+            .iter()
+            .filter(|rule| rule.is_enabled)
+            .map(|rule| (rule.name.clone(), CompiledImportRule::compile(rule)))
+            .collect::<Vec<_>>();
+        let mut media_entries = self.get_selected_media_entries();
+        if media_entries.is_empty() {
+            media_entries = self.get_importable_media_entries();
+        }
+        media_entries
+            .iter()
+            .map(|media_entry| {
+                let media_entry = media_entry.borrow();
+                let fired_rules = compiled_rules
+                    .iter()
+                    .filter_map(|(rule_name, compiled_rule)| match compiled_rule {
+                        Ok(compiled_rule) => match media_entry.matches_rule(compiled_rule) {
+                            Ok(true) => Some(rule_name.clone()),
+                            Ok(false) => None,
+                            Err(e) => Some(format!("{rule_name}: {e}")),
+                        },
+                        Err(e) => Some(format!("{rule_name}: {e}")),
+                    })
+                    .collect::<Vec<_>>();
+                (media_entry.file_label.clone(), fired_rules)
+            })
+            .collect()
+    }
+
+    fn render_rules_window(&mut self, ctx: &Context) {
+        let mut is_open = self.is_rules_window_open;
+        egui::Window::new("rules").open(&mut is_open).show(ctx, |ui| {
+            ui.label("shows which import rules would apply to the selected entries (or all entries if none are selected)");
+            if ui.add_enabled(self.importation_entries.is_some(), Button::new("run dry run")).clicked() {
+                self.rules_dry_run = Some(self.dry_run_rules());
+            }
+            if let Some(rules_dry_run) = self.rules_dry_run.as_ref() {
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(400.).show(ui, |ui| {
+                    egui::Grid::new("rules_dry_run").striped(true).show(ui, |ui| {
+                        for (file_label, fired_rules) in rules_dry_run {
+                            ui.label(file_label);
+                            if fired_rules.is_empty() {
+                                ui.weak("no rules");
+                            } else {
+                                ui.label(fired_rules.join(", "));
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            }
+        });
+        self.is_rules_window_open = is_open;
+    }
+
+    // re-applies the pool ordering, e.g. after the sequence regex was changed in the config
+    fn resequence_pools(&self) -> Result<()> {
+        let media_entries = self.get_importable_media_entries();
+        let members = media_entries
+            .iter()
+            .map(|media_entry| {
+                let media_entry = media_entry.borrow();
+                (media_entry.linking_dir.clone(), media_entry.file_label.clone())
+            })
+            .collect::<Vec<_>>();
+        let positions = import::pool_positions(&members, import::sequence_regex()?.as_ref());
+        for (media_entry, position) in media_entries.iter().zip(positions) {
+            media_entry.borrow_mut().linking_value = position;
+        }
+        Ok(())
+    }
+
+    fn render_pool_order_window(&mut self, ctx: &Context) {
+        let mut is_open = self.is_pool_order_window_open;
+        egui::Window::new("pool order").open(&mut is_open).show(ctx, |ui| {
+            ui.label("the order pool members will be imported in");
+            if ui.add_enabled(self.importation_entries.is_some(), Button::new("re-order")).clicked() {
+                if let Err(e) = self.resequence_pools() {
+                    ui::toast_error_lock(&self.shared_state.toasts, format!("failed to order pools: {e}"));
+                }
+            }
+            let mut pools: Vec<(String, Vec<(Option<i32>, String)>)> = vec![];
+            for media_entry in self.get_importable_media_entries() {
+                let media_entry = media_entry.borrow();
+                if let Some(linking_dir) = media_entry.linking_dir.as_ref() {
+                    let member = (media_entry.linking_value, media_entry.file_label.clone());
+                    match pools.iter_mut().find(|(pool_dir, _)| pool_dir == linking_dir) {
+                        Some((_, members)) => members.push(member),
+                        None => pools.push((linking_dir.clone(), vec![member])),
+                    }
+                }
+            }
+            if pools.is_empty() {
+                ui.weak("no pools found");
+                return;
+            }
+            ui.separator();
+            ScrollArea::vertical().max_height(400.).show(ui, |ui| {
+                for (linking_dir, mut members) in pools {
+                    members.sort_by_key(|(linking_value, _)| *linking_value);
+                    egui::CollapsingHeader::new(format!("{linking_dir} ({} media)", members.len())).show(ui, |ui| {
+                        egui::Grid::new(format!("pool_order_{linking_dir}")).striped(true).show(ui, |ui| {
+                            for (linking_value, file_label) in members {
+                                ui.label(linking_value.map(|value| value.to_string()).unwrap_or(String::from("?")));
+                                ui.label(file_label);
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+            });
+        });
+        self.is_pool_order_window_open = is_open;
+    }
+
+    fn render_near_duplicates_window(&mut self, ctx: &Context) {
+        let mut is_open = self.is_near_duplicates_window_open;
+        egui::Window::new("near duplicates").open(&mut is_open).show(ctx, |ui| {
+            let is_checking = self.near_duplicate_check.is_some();
+            if ui
+                .add_enabled(
+                    self.importation_entries.is_some() && !is_checking,
+                    Button::new(if is_checking { "checking..." } else { "check library" }),
+                )
+                .clicked()
+            {
+                self.start_near_duplicate_check();
+            }
+            let near_duplicate_entries = self.filter_media_entries(|media_entry| media_entry.borrow().near_duplicate.is_some());
+            if near_duplicate_entries.is_empty() {
+                ui.weak("no possible duplicates found");
+                return;
+            }
+            ui.separator();
+            let thumbnail_size = Config::global().ui.import_thumbnail_size as f32;
+            ScrollArea::vertical().max_height(500.).show(ui, |ui| {
+                for media_entry in near_duplicate_entries {
+                    let mut media_entry = media_entry.borrow_mut();
+                    let media_entry = &mut *media_entry;
+                    let file_label = &media_entry.file_label;
+                    let near_duplicate = match media_entry.near_duplicate.as_mut() {
+                        Some(near_duplicate) => near_duplicate,
+                        None => continue,
+                    };
+                    ui.horizontal(|ui| {
+                        let mut options = ui::RenderLoadingImageOptions::default();
+                        options.desired_image_size = [thumbnail_size, thumbnail_size];
+                        options.hover_text = Some(file_label.clone().into());
+                        ui::render_loading_preview(ui, ctx, media_entry.thumbnail.as_mut(), &options);
+                        options.hover_text = Some(EntryId::MediaEntry(near_duplicate.hash.clone()).to_string().into());
+                        ui::render_loading_preview(ui, ctx, near_duplicate.library_thumbnail.as_mut(), &options);
+
+                        ui.vertical(|ui| {
+                            let readable_dimensions = |dimensions: Option<(u32, u32)>| {
+                                dimensions.map(|(width, height)| format!("{width}x{height}")).unwrap_or(String::from("?"))
+                            };
+                            ui.label(file_label);
+                            ui.label(format!(
+                                "possible duplicate of {} (distance {})",
+                                EntryId::MediaEntry(near_duplicate.hash.clone()),
+                                near_duplicate.distance
+                            ));
+                            ui.label(format!(
+                                "{} here, {} in library",
+                                readable_dimensions(near_duplicate.dimensions),
+                                readable_dimensions(near_duplicate.library_dimensions)
+                            ));
+                            ui.horizontal(|ui| {
+                                ui.selectable_value(
+                                    &mut near_duplicate.action,
+                                    NearDuplicateAction::Skip,
+                                    NearDuplicateAction::Skip.to_string(),
+                                );
+                                ui.selectable_value(
+                                    &mut near_duplicate.action,
+                                    NearDuplicateAction::ImportAnyway,
+                                    NearDuplicateAction::ImportAnyway.to_string(),
+                                );
+                                if near_duplicate.can_replace() {
+                                    ui.selectable_value(
+                                        &mut near_duplicate.action,
+                                        NearDuplicateAction::Replace,
+                                        NearDuplicateAction::Replace.to_string(),
+                                    );
+                                }
+                            });
+                        });
+                    });
+                    ui.separator();
+                }
+            });
+        });
+        self.is_near_duplicates_window_open = is_open;
+    }
+
     fn render_extraction_prompt(&mut self, ctx: &Context) -> Modal {
         let prompt_show = ui::modal(ctx, "extraction_progress");
         let prompt_ask = ui::modal(ctx, "extraction_prompt");
@@ -901,6 +1762,7 @@ impl ImporterUI {
     }
 
     fn render_import_status_window(&mut self, ctx: &egui::Context) {
+        let mut requested_report = None;
         egui::Window::new("import status")
             .open(&mut self.is_import_status_window_open)
             .resizable(false)
@@ -909,6 +1771,9 @@ impl ImporterUI {
                     let mut total_failed = 0;
                     let mut total_succeeded = 0;
                     let mut total_duplicates = 0;
+                    let mut total_duplicates_added_to_pool = 0;
+                    let mut total_duplicates_merged = 0;
+                    let mut total_previously_deleted = 0;
                     let mut total_not_started = 0;
                     let mut total_currently_importing = 0;
                     let mut total_not_selected_for_import = 0;
@@ -918,7 +1783,15 @@ impl ImporterUI {
                                 match importation_status {
                                     ImportationStatus::Pending => total_not_started += 1,
                                     ImportationStatus::Success => total_succeeded += 1,
-                                    ImportationStatus::Duplicate => total_duplicates += 1,
+                                    ImportationStatus::Duplicate(duplicate_action) => {
+                                        total_duplicates += 1;
+                                        match duplicate_action {
+                                            DuplicateAction::AddToPool => total_duplicates_added_to_pool += 1,
+                                            DuplicateAction::MergeTags => total_duplicates_merged += 1,
+                                            DuplicateAction::Skip => (),
+                                        }
+                                    }
+                                    ImportationStatus::PreviouslyDeleted => total_previously_deleted += 1,
                                     ImportationStatus::Fail(_) => total_failed += 1,
                                 }
                             } else {
@@ -933,7 +1806,7 @@ impl ImporterUI {
                     ui.vertical_centered(|ui| {
                         ui.label(format!(
                             "{} / {total_selected_for_import} entries processed",
-                            total_succeeded + total_duplicates + total_failed
+                            total_succeeded + total_duplicates + total_previously_deleted + total_failed
                         ));
                         ui.separator();
                         ui.label(format!("{total_currently_importing} entries currently processing"));
@@ -941,18 +1814,37 @@ impl ImporterUI {
                         ui.label(format!("{total_succeeded} import successes"));
                         ui.separator();
                         ui.label(format!("{total_duplicates} duplicate entries"));
+                        ui.label(format!("{total_duplicates_added_to_pool} added to new pools"));
+                        ui.label(format!("{total_duplicates_merged} had tags merged"));
+                        ui.label(format!("{total_previously_deleted} previously deleted"));
                         ui.separator();
                         ui.label(format!("{total_failed} import failures"));
+                        if let Some(session_id) = self.import_session_id {
+                            ui.separator();
+                            if ui
+                                .button("report")
+                                .on_hover_text("every file's outcome, exportable as csv or json")
+                                .clicked()
+                            {
+                                requested_report = Some(session_id);
+                            }
+                        }
                     });
                 }
             });
+        if let Some(session_id) = requested_report {
+            self.load_import_report(session_id);
+        }
     }
 }
 
 impl ui::UserInterface for ImporterUI {
     fn ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
         self.process_media();
+        self.process_near_duplicate_check();
         self.process_extractions();
+        self.process_import_session();
+        self.process_scan();
         self.process_dropped_files(ctx);
         self.render_scan_directory_selection(ui);
         ui.separator();
@@ -994,7 +1886,81 @@ impl ui::UserInterface for ImporterUI {
         }
         self.render_import_status_window(ctx);
         self.render_filters_window(ctx);
+        self.render_rules_window(ctx);
+        self.render_pool_order_window(ctx);
+        self.render_near_duplicates_window(ctx);
+        self.render_unfinished_session_window(ctx);
+        self.render_sessions_window(ctx);
+        self.render_report_window(ctx);
+        self.render_scan_errors_window(ctx);
 
         self.render_dropping_files(ui, ctx);
     }
 }
+
+pub struct ClipboardImportModal {
+    shared_state: Rc<SharedState>,
+    id: &'static str,
+    tagstrings: String,
+}
+
+impl ClipboardImportModal {
+    pub fn new(shared_state: &Rc<SharedState>, id: &'static str) -> Self {
+        Self {
+            shared_state: Rc::clone(shared_state),
+            id,
+            tagstrings: String::new(),
+        }
+    }
+
+    pub fn render(&mut self, ctx: &Context) -> Modal {
+        let modal = ui::modal(ctx, self.id);
+        modal.show(|ui| {
+            modal.title(ui, "import clipboard");
+            modal.frame(ui, |ui| {
+                ui.label("import the clipboard image or copied files, with these tags:");
+                if let Some(options) = self.shared_state.autocomplete_options.borrow().as_ref() {
+                    ui.add(autocomplete::create(&mut self.tagstrings, options, false, true));
+                } else {
+                    ui.text_edit_singleline(&mut self.tagstrings);
+                }
+            });
+            modal.buttons(ui, |ui| {
+                modal.button(ui, "cancel");
+                if modal.suggested_button(ui, icon!("import", IMPORT_ICON)).clicked() {
+                    self.import();
+                }
+            });
+        });
+        modal
+    }
+
+    fn import(&mut self) {
+        let tags = Tag::from_tagstrings(&self.tagstrings);
+        let toasts = Arc::clone(&self.shared_state.toasts);
+        let gallery_regenerate_flag = Arc::clone(&self.shared_state.gallery_regenerate_flag);
+        let tag_data_update_flag = Arc::clone(&self.shared_state.tag_data_update_flag);
+        self.tagstrings.clear();
+        thread::spawn(
+            move || match import::read_clipboard_media().and_then(|media| import::import_clipboard_media(media, tags)) {
+                Ok(summary) => {
+                    if summary.imported_count > 0 {
+                        ui::toast_success_lock(&toasts, format!("imported {} media from the clipboard", summary.imported_count));
+                        SharedState::raise_update_flag(&gallery_regenerate_flag);
+                        SharedState::raise_update_flag(&tag_data_update_flag);
+                    }
+                    if summary.duplicate_count > 0 {
+                        ui::toast_warning_lock(&toasts, format!("{} clipboard media already in the library", summary.duplicate_count));
+                    }
+                    if summary.deleted_count > 0 {
+                        ui::toast_warning_lock(&toasts, format!("{} clipboard media were deleted before", summary.deleted_count));
+                    }
+                    for failure in summary.failures {
+                        ui::toast_error_lock(&toasts, format!("failed to import {failure}"));
+                    }
+                }
+                Err(e) => ui::toast_error_lock(&toasts, format!("failed to import clipboard: {e}")),
+            },
+        );
+    }
+}
@@ -1,7 +1,57 @@
 use anyhow::Result;
 use parking_lot::Mutex;
 use poll_promise::Promise;
-use std::{cell::RefCell, rc::Rc, sync::Arc};
+use std::{cell::RefCell, cmp::Ordering, iter::Peekable, rc::Rc, str::Chars, sync::Arc};
+use std::{fs::File, io::Read, path::Path, thread};
+
+use crate::formats;
+
+// enough of a file's head to cover every signature infer checks
+const MIME_SNIFF_LENGTH: u64 = 8192;
+
+// works the mime type out from the magic bytes, whatever the file is named
+pub fn sniff_mime(bytes: &[u8]) -> Option<mime_guess::Mime> {
+    infer::get(bytes).and_then(|kind| formats::canonical_mime(kind.mime_type()).parse().ok())
+}
+
+// runs job over the items on every core, results come back in the same order as the items.
+// a job that panicked leaves a None in its place
+pub fn map_with_workers<T: Send + 'static, R: Send + 'static>(items: Vec<T>, job: fn(T) -> R) -> Vec<Option<R>> {
+    let item_count = items.len();
+    let jobs = Arc::new(Mutex::new(items.into_iter().enumerate().collect::<Vec<_>>()));
+    let results = Arc::new(Mutex::new((0..item_count).map(|_| None).collect::<Vec<Option<R>>>()));
+    let worker_count = thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(1)
+        .min(item_count.max(1));
+    let workers = (0..worker_count)
+        .map(|_| {
+            let jobs = Arc::clone(&jobs);
+            let results = Arc::clone(&results);
+            thread::spawn(move || loop {
+                let next_job = jobs.lock().pop();
+                match next_job {
+                    Some((index, item)) => {
+                        let result = job(item);
+                        results.lock()[index] = Some(result);
+                    }
+                    None => break,
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for worker in workers {
+        let _ = worker.join();
+    }
+    let mut results = results.lock();
+    results.iter_mut().map(|result| result.take()).collect()
+}
+
+pub fn sniff_mime_of_file(path: &Path) -> Option<mime_guess::Mime> {
+    let mut head = vec![];
+    File::open(path).ok()?.take(MIME_SNIFF_LENGTH).read_to_end(&mut head).ok()?;
+    sniff_mime(&head)
+}
 
 pub struct PollBuffer<T> {
     pub entries: Vec<Rc<RefCell<T>>>,
@@ -278,3 +328,68 @@ pub fn opt_vec_applyeach_refcell<T>(opt_vec: &mut Option<Vec<Rc<RefCell<T>>>>, f
         vec.iter_mut().for_each(|item| f(&mut item.borrow_mut()))
     }
 }
+
+/// compares strings the way a person would order them, so "page_2" comes before "page_10"
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_char), Some(b_char)) if a_char.is_ascii_digit() && b_char.is_ascii_digit() => {
+                let take_number = |chars: &mut Peekable<Chars>| {
+                    let mut digits = String::new();
+                    while let Some(digit) = chars.next_if(|c| c.is_ascii_digit()) {
+                        digits.push(digit);
+                    }
+                    digits
+                };
+                let (a_digits, b_digits) = (take_number(&mut a_chars), take_number(&mut b_chars));
+                let (a_trimmed, b_trimmed) = (a_digits.trim_start_matches('0'), b_digits.trim_start_matches('0'));
+                let ordering = a_trimmed.len().cmp(&b_trimmed.len()).then_with(|| a_trimmed.cmp(b_trimmed));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(a_char), Some(b_char)) => {
+                let ordering = a_char.to_lowercase().cmp(b_char.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_cmp_orders_digit_runs_by_value() {
+        assert_eq!(natural_cmp("page2", "page10"), Ordering::Less);
+        assert_eq!(natural_cmp("page10", "page2"), Ordering::Greater);
+        assert_eq!(natural_cmp("vol2_page10", "vol10_page2"), Ordering::Less);
+        assert_eq!(natural_cmp("page", "page1"), Ordering::Less);
+        assert_eq!(natural_cmp("page2", "page2"), Ordering::Equal);
+    }
+
+    #[test]
+    fn natural_cmp_ignores_leading_zeros() {
+        assert_eq!(natural_cmp("page007", "page10"), Ordering::Less);
+        assert_eq!(natural_cmp("page010", "page9"), Ordering::Greater);
+        // the same value is told apart by the plain ordering, so sorting stays deterministic
+        assert_eq!(natural_cmp("page02", "page2"), Ordering::Less);
+    }
+
+    #[test]
+    fn natural_cmp_ignores_case() {
+        assert_eq!(natural_cmp("Page2", "page10"), Ordering::Less);
+        assert_eq!(natural_cmp("apple", "Banana"), Ordering::Less);
+        assert_eq!(natural_cmp("ABC", "abc"), "ABC".cmp("abc"));
+    }
+}